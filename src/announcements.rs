@@ -0,0 +1,156 @@
+// Anuncios: actividades de una sola vez ("Recién publicado: ...") que se
+// ponen en cola y se muestran por orden antes que la rotación, cada una
+// durante su tiempo y con cuenta atrás. Al vaciarse la cola la rotación
+// sigue por donde iba. Nunca se guardan en la configuración:
+//   {"command": "announce", "args": {"activity": {"details": "Recién publicado", "state": "Nuevo dibujo"}, "duration": 120}}
+//   {"command": "announce", "args": {"activity": "cute_mode"}}
+//
+// Como el override, se muestran aunque haya horarios u horas de silencio. Un
+// override activo tiene prioridad: los anuncios esperan a que termine.
+
+use super::*;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+// Tiempo en pantalla si la petición no indica uno
+pub const DEFAULT_DURATION_SECONDS: u64 = 120;
+// Un anuncio que lleva más que esto en la cola (sin conexión, detrás de un
+// override largo...) ya no es noticia y se descarta
+const MAX_WAIT: Duration = Duration::from_secs(30 * 60);
+
+// Nombre de los anuncios indicados en la petición que no traen uno
+const INLINE_NAME: &str = "anuncio";
+
+#[derive(Debug)]
+struct Queued {
+    activity: ActivityConfig,
+    duration: Duration,
+    queued_at: Instant,
+}
+
+// Cola de anuncios y el que se está mostrando
+#[derive(Debug, Default)]
+pub struct Announcements {
+    queue: VecDeque<Queued>,
+    pub current: Option<overrides::TimedActivity>,
+}
+
+impl Announcements {
+    pub fn push(&mut self, activity: ActivityConfig, duration: Duration) {
+        self.queue.push_back(Queued {
+            activity,
+            duration,
+            queued_at: Instant::now(),
+        });
+    }
+
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
+    // Termina el anuncio actual si ya pasó su tiempo. Devuelve true si acaba
+    // de terminar
+    pub fn finish(&mut self) -> bool {
+        if !self
+            .current
+            .as_ref()
+            .is_some_and(|current| current.is_expired())
+        {
+            return false;
+        }
+        if let Some(current) = self.current.take() {
+            log_info(&format!("⌛ Anuncio '{}' terminado", current.activity.name));
+        }
+        true
+    }
+
+    // Quita el anuncio que se está mostrando (next, select); la cola sigue
+    pub fn skip(&mut self) -> bool {
+        match self.current.take() {
+            Some(current) => {
+                log_info(&format!("📣 Anuncio '{}' saltado", current.activity.name));
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Saca el siguiente de la cola si no se está mostrando ninguno. Devuelve
+    // true si hay uno nuevo
+    pub fn start_next(&mut self) -> bool {
+        if self.current.is_some() {
+            return false;
+        }
+        while let Some(queued) = self.queue.pop_front() {
+            if queued.queued_at.elapsed() > MAX_WAIT {
+                log_info(&format!(
+                    "🗑️  Anuncio '{}' descartado: llevaba más de {} en la cola",
+                    queued.activity.name,
+                    overrides::format_remaining(MAX_WAIT)
+                ));
+                continue;
+            }
+            log_info(&format!(
+                "📣 Anuncio '{}' durante {}",
+                queued.activity.name,
+                overrides::format_remaining(queued.duration)
+            ));
+            self.current = Some(overrides::TimedActivity::new(
+                queued.activity,
+                queued.duration,
+            ));
+            return true;
+        }
+        false
+    }
+
+    // Vacía la cola y quita el actual. Devuelve cuántos había en total
+    pub fn clear(&mut self) -> usize {
+        let count = self.queue.len() + usize::from(self.current.is_some());
+        self.queue.clear();
+        self.current = None;
+        count
+    }
+}
+
+// Igual que en el override, pero un anuncio sin nombre se llama "anuncio"
+pub fn resolve(
+    activity: OverrideActivity,
+    activities: &[ActivityConfig],
+) -> StdResult<ActivityConfig, String> {
+    let mut activity = overrides::resolve(activity, activities)?;
+    if activity.name == overrides::INLINE_NAME {
+        activity.name = INLINE_NAME.to_string();
+    }
+    Ok(activity)
+}
+
+// "announce <actividad> [duración]" en la línea de comandos. La actividad
+// puede ser un nombre (con espacios) o un objeto JSON; "announce clear"
+// vacía la cola
+pub fn parse_cli(args: &[String]) -> StdResult<AppCommand, String> {
+    let usage = "Uso: announce <actividad|{json}> [duración] (p. ej. announce {\"details\": \"Recién publicado\"} 2m) o announce clear";
+    if args.len() == 1 && args[0] == "clear" {
+        return Ok(AppCommand::ClearAnnouncements);
+    }
+    if args.is_empty() {
+        return Err(usage.to_string());
+    }
+
+    // El último argumento es la duración solo si se puede leer como tal
+    let (activity, duration) = match args.split_last() {
+        Some((last, rest)) if !rest.is_empty() => match timestamps::parse_duration(last) {
+            Ok(duration) => (rest, Some(duration as u64)),
+            Err(_) => (args, None),
+        },
+        _ => (args, None),
+    };
+
+    let activity = activity.join(" ");
+    let activity = if activity.trim_start().starts_with('{') {
+        serde_json::from_str(&activity).map_err(|e| format!("Actividad JSON no válida: {}", e))?
+    } else {
+        OverrideActivity::Named(activity)
+    };
+    Ok(AppCommand::Announce { activity, duration })
+}
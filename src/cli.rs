@@ -0,0 +1,292 @@
+// Subcomandos de línea de comandos. Sin argumentos se arranca la app normal;
+// con un subcomando se habla con la instancia en ejecución a través del
+// socket de control, se imprime el resultado y se sale.
+
+use super::*;
+
+pub enum CliAction {
+    Run,
+    Help,
+    // Asistente interactivo para crear config.json
+    Setup,
+    // Validar un archivo de configuración sin arrancar la app
+    Validate(Option<PathBuf>),
+    // Reescribir la configuración en otro formato
+    Convert {
+        format: formats::ConfigFormat,
+        source: Option<PathBuf>,
+        force: bool,
+    },
+    Send(AppCommand),
+}
+
+// Separa --config y --data-dir (valen con cualquier subcomando, también
+// para hablar con una instancia que se arrancó con ellos) del resto
+pub fn split_path_options(args: &[String]) -> StdResult<(paths::Overrides, Vec<String>), String> {
+    let mut overrides = paths::Overrides::default();
+    let mut rest = Vec::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let (option, inline_value) = match arg.split_once('=') {
+            Some((option, value)) => (option, Some(value.to_string())),
+            None => (arg.as_str(), None),
+        };
+        let target = match option {
+            "--config" => &mut overrides.config_file,
+            "--data-dir" => &mut overrides.data_dir,
+            _ => {
+                rest.push(arg.clone());
+                continue;
+            }
+        };
+        let value = inline_value
+            .or_else(|| args.next().cloned())
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| format!("Falta la ruta después de {}", option))?;
+        *target = Some(PathBuf::from(value));
+    }
+    Ok((overrides, rest))
+}
+
+pub fn parse_args(args: &[String]) -> StdResult<CliAction, String> {
+    let Some(subcommand) = args.first() else {
+        return Ok(CliAction::Run);
+    };
+    let rest = &args[1..];
+
+    let action = match subcommand.as_str() {
+        "run" => CliAction::Run,
+        "help" | "-h" | "--help" => CliAction::Help,
+        "setup" => CliAction::Setup,
+        "status" => CliAction::Send(AppCommand::ShowStatus),
+        "pause" => CliAction::Send(AppCommand::Pause),
+        "resume" => CliAction::Send(AppCommand::Resume),
+        "next" => CliAction::Send(AppCommand::NextActivity),
+        "reload" => CliAction::Send(AppCommand::ReloadConfig),
+        "stop" => CliAction::Send(AppCommand::Exit),
+        "validate" => {
+            return match rest {
+                [] => Ok(CliAction::Validate(None)),
+                [path] => Ok(CliAction::Validate(Some(PathBuf::from(path)))),
+                _ => Err("Uso: validate [ruta/a/config]".to_string()),
+            };
+        }
+        "convert" => {
+            let force = rest.iter().any(|arg| arg == "--force");
+            let args: Vec<&String> = rest.iter().filter(|arg| *arg != "--force").collect();
+            let usage = "Uso: convert <json|json5|toml|yaml> [ruta/a/config] [--force]";
+            return match args.as_slice() {
+                [format] | [format, _] => {
+                    let format = formats::ConfigFormat::from_name(format)
+                        .ok_or_else(|| format!("Formato desconocido: '{}'. {}", format, usage))?;
+                    Ok(CliAction::Convert {
+                        format,
+                        source: args.get(1).map(PathBuf::from),
+                        force,
+                    })
+                }
+                _ => Err(usage.to_string()),
+            };
+        }
+        "select" => {
+            // Permite nombres con espacios sin comillas: select cute mode
+            if rest.is_empty() {
+                return Err("Falta el nombre de la actividad: select <actividad>".to_string());
+            }
+            return Ok(CliAction::Send(AppCommand::SelectActivity(rest.join(" "))));
+        }
+        "override" => return overrides::parse_cli(rest).map(CliAction::Send),
+        "announce" => return announcements::parse_cli(rest).map(CliAction::Send),
+        "profile" => {
+            if rest.is_empty() {
+                return Err("Falta el nombre del perfil: profile <perfil>".to_string());
+            }
+            return Ok(CliAction::Send(AppCommand::SwitchProfile(rest.join(" "))));
+        }
+        other => return Err(format!("Subcomando desconocido: '{}'", other)),
+    };
+
+    if !rest.is_empty() {
+        return Err(format!(
+            "El subcomando '{}' no acepta argumentos: {}",
+            subcommand,
+            rest.join(" ")
+        ));
+    }
+    Ok(action)
+}
+
+// En Windows el ejecutable no tiene consola propia (subsystem windows), así
+// que hay que engancharse a la de la terminal que lo lanzó para imprimir
+#[cfg(windows)]
+pub fn attach_console() {
+    unsafe {
+        let _ = AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+pub fn attach_console() {}
+
+pub fn print_usage() {
+    attach_console();
+    println!("Uso: lorianworkspace [--config <archivo>] [--data-dir <dir>] [subcomando]");
+    println!();
+    println!("Sin subcomando arranca Lorian Workspace en segundo plano.");
+    println!();
+    println!("Opciones:");
+    println!("  --config <archivo>  Usa ese archivo de configuración");
+    println!("  --data-dir <dir>    Guarda configuración, logs y estado en ese directorio");
+    println!();
+    println!("Subcomandos para controlar la instancia en ejecución:");
+    println!("  status              Muestra la actividad actual y el estado");
+    println!("  pause               Pausa la rotación");
+    println!("  resume              Reanuda la rotación");
+    println!("  next                Pasa a la siguiente actividad");
+    println!("  reload              Recarga la configuración");
+    println!("  select <actividad>  Muestra la actividad con ese nombre");
+    println!("  profile <perfil>    Cambia al perfil con ese nombre");
+    println!("  override <actividad|{{json}}> <duración>");
+    println!("                      Muestra esa actividad durante un tiempo (10m, 1h30m)");
+    println!("                      y después sigue la rotación; override off lo termina");
+    println!("  announce <actividad|{{json}}> [duración]");
+    println!("                      Pone en cola un anuncio de una sola vez (2m por defecto);");
+    println!("                      announce clear vacía la cola");
+    println!("  stop                Cierra la aplicación");
+    println!();
+    println!("Otros subcomandos:");
+    println!("  setup               Asistente para configurar el Application ID y actividades");
+    println!("  validate [archivo]  Revisa la configuración sin arrancar la app");
+    println!("  convert <formato> [archivo] [--force]");
+    println!("                      Convierte la configuración a json, json5, toml o yaml");
+    println!("  help                Muestra esta ayuda");
+}
+
+fn print_status(status: &control::StatusSnapshot) {
+    match &status.current_activity {
+        Some(activity) => {
+            println!(
+                "📊 Actividad actual: {} ({}/{})",
+                activity.name,
+                activity.index + 1,
+                status.activity_count
+            );
+            println!("   📝 {}", activity.details);
+            println!("   📊 {}", activity.state);
+            if let Some(error) = &activity.error {
+                println!("   🚫 {}", error);
+            }
+        }
+        None => println!("❌ Sin actividades configuradas"),
+    }
+    if let Some(pinned) = &status.override_activity {
+        println!(
+            "📌 Override: {} - {} (queda {})",
+            pinned.name,
+            pinned.details,
+            overrides::format_remaining(std::time::Duration::from_secs(pinned.remaining_seconds))
+        );
+    }
+    if let Some(current) = &status.announcement {
+        println!(
+            "📣 Anuncio: {} - {} (queda {})",
+            current.name,
+            current.details,
+            overrides::format_remaining(std::time::Duration::from_secs(current.remaining_seconds))
+        );
+    }
+    if status.queued_announcements > 0 {
+        println!("📣 Anuncios en cola: {}", status.queued_announcements);
+    }
+    println!(
+        "⏸️  Rotación: {}{}",
+        if status.paused { "Pausada" } else { "Activa" },
+        if status.rotation.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", status.rotation)
+        }
+    );
+    if let Some(reason) = &status.hidden {
+        println!("🌙 Presencia oculta: {}", reason);
+    }
+    if !status.failing_activities.is_empty() {
+        println!("🚫 Rechazadas por Discord (no rotan hasta que cambien):");
+        for failing in &status.failing_activities {
+            println!(
+                "   {} ({}): {}",
+                failing.name, failing.code, failing.message
+            );
+        }
+    }
+    let connection = &status.connection;
+    match (status.connected, connection.next_retry_seconds) {
+        (true, _) => println!("🔗 Conexión: ✅ Conectado"),
+        (false, Some(seconds)) if connection.attempts > 0 => println!(
+            "🔗 Conexión: ❌ Desconectado ({}) - reintento {} en {}",
+            connection.state,
+            connection.attempts,
+            overrides::format_remaining(std::time::Duration::from_secs(seconds))
+        ),
+        (false, _) if matches!(connection.state.as_str(), "connecting" | "handshaking") => {
+            println!("🔗 Conexión: 🔄 Conectando ({})", connection.state)
+        }
+        (false, _) => println!("🔗 Conexión: ❌ Desconectado ({})", connection.state),
+    }
+    if let (false, Some(error)) = (status.connected, &connection.last_error) {
+        println!("   Último error: {}", error);
+    }
+    // Con un solo perfil no hace falta mencionarlo
+    if status.profiles.len() > 1 {
+        println!(
+            "🗂️  Perfil: {} (disponibles: {})",
+            status.profile,
+            status.profiles.join(", ")
+        );
+    }
+    println!("🆔 Application ID: {}", status.app_id);
+    if let Some(switch) = &status.last_app_id_switch {
+        println!("🔀 Último cambio de Application ID: {}", switch);
+    }
+    if let Some(migration) = &status.last_migration {
+        println!("🔧 {}", migration);
+    }
+    if let Some(error) = &status.config_error {
+        println!("⚠️  Usando la última configuración válida - {}", error);
+    }
+    println!("📁 Config: {}", status.config_path);
+    println!("📂 Logs y estado: {}", status.state_dir);
+}
+
+// Devuelve el código de salida del proceso
+pub async fn run_client(command: AppCommand) -> i32 {
+    attach_console();
+
+    let is_status = matches!(command, AppCommand::ShowStatus);
+    let response = match control::send_command(&command).await {
+        Ok(response) => response,
+        Err(e) => {
+            eprintln!("❌ No hay ninguna instancia de Lorian Workspace en ejecución");
+            eprintln!("   {}", e);
+            return 1;
+        }
+    };
+
+    if is_status {
+        if let Some(status) = &response.status {
+            print_status(status);
+        }
+    } else if let Some(message) = &response.message {
+        println!("{}", message);
+    }
+
+    match &response.error {
+        Some(error) => {
+            eprintln!("{}", error);
+            1
+        }
+        None if response.ok => 0,
+        None => 1,
+    }
+}
@@ -0,0 +1,341 @@
+// Conexión con Discord como una máquina de estados:
+//
+//   Disconnected ──connect──▶ Connecting ──▶ Handshaking ──▶ Ready
+//        ▲                        │               │            │
+//        │ close                  └──── fallo ────┴── error ───┘
+//        │                                  ▼
+//        └──────────────────────────── Backoff ──(espera)──▶ Connecting
+//
+// Un error permanente (Application ID rechazado, ver presence.rs) lleva a
+// Failed: no se reintenta hasta que cambie el Application ID.
+//
+// Tras cada fallo seguido se espera el doble que la vez anterior, desde
+// initial_delay_seconds hasta max_delay_seconds ("reconnect" en la sección
+// discord o en cada perfil), con un ±20 % al azar para que varias apps no
+// reintenten a la vez cuando Discord vuelve.
+
+use super::*;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+pub const DEFAULT_INITIAL_DELAY_SECONDS: u64 = 2;
+pub const DEFAULT_MAX_DELAY_SECONDS: u64 = 300;
+// Parte de la espera que se varía al azar, en tanto por ciento
+const JITTER_PERCENT: u64 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    Disconnected,
+    Connecting,
+    Handshaking,
+    Ready,
+    Backoff,
+    Failed,
+}
+
+impl ConnectionState {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Disconnected => "disconnected",
+            Self::Connecting => "connecting",
+            Self::Handshaking => "handshaking",
+            Self::Ready => "ready",
+            Self::Backoff => "backoff",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Connection {
+    state: ConnectionState,
+    // Solo hay cliente en Ready
+    client: Option<DiscordIpcClient>,
+    // Intentos fallidos seguidos desde la última conexión buena
+    attempts: u32,
+    next_retry: Option<Instant>,
+    last_error: Option<String>,
+    // Número del último intento de conexión empezado
+    attempt: u64,
+    initial_delay: Duration,
+    max_delay: Duration,
+}
+
+// Abre el socket de Discord (el primer paso de un intento)
+pub fn open(app_id: &str) -> StdResult<DiscordIpcClient, presence::PresenceError> {
+    let mut client = DiscordIpcClient::new(app_id)
+        .map_err(|e| presence::PresenceError::Unavailable(e.to_string()))?;
+    client
+        .connect_ipc()
+        .map_err(|e| presence::PresenceError::Unavailable(e.to_string()))?;
+    Ok(client)
+}
+
+// Hace el handshake (el segundo paso). Si Discord lo rechaza se cierra el
+// cliente
+pub async fn handshake(
+    client: DiscordIpcClient,
+    app_id: &str,
+) -> StdResult<DiscordIpcClient, presence::PresenceError> {
+    let app_id = app_id.to_string();
+    let (mut client, ready) =
+        presence::call(client, move |client| presence::handshake(client, &app_id)).await?;
+    match ready {
+        Ok(ready) => {
+            if let Some(user) = ready["user"]["username"].as_str() {
+                log_info(&format!("👤 Discord listo para {}", user));
+            }
+            Ok(client)
+        }
+        Err(e) => {
+            let _ = client.close();
+            Err(e)
+        }
+    }
+}
+
+// Cierra el cliente de un intento que ya no sirve
+fn abandon(mut client: DiscordIpcClient) -> presence::PresenceError {
+    let _ = client.close();
+    presence::PresenceError::Io("el intento de conexión se canceló".to_string())
+}
+
+// Para mostrar las esperas en segundos sin que un 0,8 s salga como "0s"
+fn round_up(wait: Duration) -> Duration {
+    Duration::from_secs(wait.as_secs_f64().ceil() as u64)
+}
+
+impl Connection {
+    pub fn new(settings: Option<&ReconnectConfig>) -> Self {
+        let mut connection = Self {
+            state: ConnectionState::Disconnected,
+            client: None,
+            attempts: 0,
+            next_retry: None,
+            last_error: None,
+            attempt: 0,
+            initial_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+        };
+        connection.apply_settings(settings);
+        connection
+    }
+
+    pub fn apply_settings(&mut self, settings: Option<&ReconnectConfig>) {
+        let (initial, max) = settings
+            .map(|settings| (settings.initial_delay_seconds, settings.max_delay_seconds))
+            .unwrap_or((DEFAULT_INITIAL_DELAY_SECONDS, DEFAULT_MAX_DELAY_SECONDS));
+        self.initial_delay = Duration::from_secs(initial.max(1));
+        self.max_delay = Duration::from_secs(max.max(initial).max(1));
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        self.state
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.state == ConnectionState::Ready && self.client.is_some()
+    }
+
+    // Petición a Discord con la conexión lista (ver presence::call). Si no
+    // contesta a tiempo el cliente se pierde y el error es Io
+    pub async fn request<T, F>(&mut self, request: F) -> StdResult<T, presence::PresenceError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut DiscordIpcClient) -> StdResult<T, presence::PresenceError> + Send + 'static,
+    {
+        let client = match (self.state, self.client.take()) {
+            (ConnectionState::Ready, Some(client)) => client,
+            (_, client) => {
+                self.client = client;
+                return Err(presence::PresenceError::Io(
+                    "Discord no está conectado".to_string(),
+                ));
+            }
+        };
+        let (client, result) = presence::call(client, request).await?;
+        self.client = Some(client);
+        result
+    }
+
+    // Tiempo hasta el próximo intento (cero si ya se puede)
+    pub fn retry_in(&self) -> Option<Duration> {
+        match self.state {
+            ConnectionState::Backoff => self
+                .next_retry
+                .map(|next_retry| next_retry.saturating_duration_since(Instant::now())),
+            ConnectionState::Disconnected => Some(Duration::ZERO),
+            _ => None,
+        }
+    }
+
+    pub fn can_attempt(&self) -> bool {
+        self.retry_in().is_some_and(|wait| wait.is_zero())
+    }
+
+    fn transition(&mut self, state: ConnectionState) {
+        if self.state != state {
+            log_info(&format!(
+                "🔌 Conexión: {} → {}",
+                self.state.name(),
+                state.name()
+            ));
+            self.state = state;
+        }
+    }
+
+    // Un intento de conexión va por pasos (ver connect en main.rs): abrir el
+    // socket y el handshake se hacen sin el lock del manager, así que cada
+    // paso comprueba que el intento sigue siendo el actual (no hubo un close
+    // ni empezó otro). start devuelve el número del intento, o None si
+    // todavía no toca
+    pub fn start(&mut self) -> Option<u64> {
+        if !self.can_attempt() {
+            return None;
+        }
+        self.client = None;
+        self.attempt += 1;
+        self.transition(ConnectionState::Connecting);
+        Some(self.attempt)
+    }
+
+    fn is_current(&self, attempt: u64, state: ConnectionState) -> bool {
+        self.attempt == attempt && self.state == state
+    }
+
+    // Socket abierto: falta el handshake
+    pub fn opened(
+        &mut self,
+        attempt: u64,
+        client: DiscordIpcClient,
+    ) -> StdResult<DiscordIpcClient, presence::PresenceError> {
+        if !self.is_current(attempt, ConnectionState::Connecting) {
+            return Err(abandon(client));
+        }
+        self.transition(ConnectionState::Handshaking);
+        Ok(client)
+    }
+
+    // Handshake aceptado: la conexión queda lista
+    pub fn established(
+        &mut self,
+        attempt: u64,
+        client: DiscordIpcClient,
+    ) -> StdResult<(), presence::PresenceError> {
+        if !self.is_current(attempt, ConnectionState::Handshaking) {
+            return Err(abandon(client));
+        }
+        self.client = Some(client);
+        self.attempts = 0;
+        self.next_retry = None;
+        self.last_error = None;
+        self.transition(ConnectionState::Ready);
+        Ok(())
+    }
+
+    // Falló un paso del intento; si ya no es el actual no cambia nada
+    pub fn attempt_failed(
+        &mut self,
+        attempt: u64,
+        error: presence::PresenceError,
+    ) -> presence::PresenceError {
+        if self.attempt == attempt
+            && matches!(
+                self.state,
+                ConnectionState::Connecting | ConnectionState::Handshaking
+            )
+        {
+            self.failed(error)
+        } else {
+            error
+        }
+    }
+
+    // La conexión falló o se perdió: esperar antes del siguiente intento, o
+    // dejar de intentarlo si el error es permanente. Devuelve el mismo error
+    // para encadenarlo
+    pub fn failed(&mut self, error: presence::PresenceError) -> presence::PresenceError {
+        if let Some(mut client) = self.client.take() {
+            let _ = client.close();
+        }
+        self.attempts = self.attempts.saturating_add(1);
+        self.last_error = Some(error.to_string());
+        if error.is_permanent() {
+            self.next_retry = None;
+            self.transition(ConnectionState::Failed);
+            log_error(&format!(
+                "⛔ {} - no se reintentará hasta cambiar el Application ID",
+                error
+            ));
+            return error;
+        }
+        let delay = self.backoff_delay();
+        self.next_retry = Some(Instant::now() + delay);
+        self.transition(ConnectionState::Backoff);
+        log_info(&format!(
+            "⏳ Reintento {} en {}",
+            self.attempts,
+            overrides::format_remaining(round_up(delay))
+        ));
+        error
+    }
+
+    // Cierre pedido por la app (salir, cambio de Application ID): el
+    // siguiente intento puede ser inmediato, también tras un Failed
+    pub fn close(&mut self) {
+        if let Some(mut client) = self.client.take() {
+            let _ = client.close();
+            log_info("Discord RPC desconectado");
+        }
+        self.attempts = 0;
+        self.next_retry = None;
+        self.last_error = None;
+        self.transition(ConnectionState::Disconnected);
+    }
+
+    fn backoff_delay(&self) -> Duration {
+        let exponent = self.attempts.saturating_sub(1).min(16);
+        let base = self
+            .initial_delay
+            .saturating_mul(1 << exponent)
+            .min(self.max_delay);
+        // RandomState trae claves nuevas cada vez: basta como azar
+        let random = RandomState::new().build_hasher().finish();
+        let spread = base.as_millis() as u64 * JITTER_PERCENT / 100;
+        let offset = if spread == 0 {
+            0
+        } else {
+            random % (2 * spread + 1)
+        };
+        (base + Duration::from_millis(offset)).saturating_sub(Duration::from_millis(spread))
+    }
+
+    // Descripción corta para el log y el estado
+    pub fn describe(&self) -> String {
+        match (self.state, self.retry_in()) {
+            (ConnectionState::Ready, _) => "✅ Conectado".to_string(),
+            (ConnectionState::Backoff, Some(wait)) => format!(
+                "❌ Desconectado - reintento {} en {}",
+                self.attempts,
+                overrides::format_remaining(round_up(wait))
+            ),
+            (ConnectionState::Failed, _) => format!(
+                "⛔ Sin reintentos: {}",
+                self.last_error.as_deref().unwrap_or_default()
+            ),
+            (state @ (ConnectionState::Connecting | ConnectionState::Handshaking), _) => {
+                format!("🔄 Conectando ({})", state.name())
+            }
+            (state, _) => format!("❌ Desconectado ({})", state.name()),
+        }
+    }
+}
@@ -0,0 +1,320 @@
+// Socket de control local: permite manejar la app desde scripts, plugins de
+// editor o la línea de comandos sin depender del system tray.
+//
+// Protocolo: una petición JSON por línea y una respuesta JSON por línea.
+//   -> {"command": "next_activity"}
+//   <- {"ok": true, "message": "⏭️ Cambiado a siguiente actividad", "status": {...}}
+//
+// En Linux/macOS es un socket Unix en el directorio de datos de la app
+// (control.sock); en Windows es un named pipe por usuario y directorio de
+// datos.
+
+use super::*;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ActivitySnapshot {
+    pub name: String,
+    pub index: usize,
+    pub details: String,
+    pub state: String,
+    pub duration_seconds: u64,
+    // Por qué Discord rechazó la actividad, si lo hizo
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// Actividad que Discord rechazó y que no rota hasta que cambie
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FailingActivitySnapshot {
+    pub name: String,
+    // Código de error de Discord (0 si no lo dio)
+    pub code: i64,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TimedActivitySnapshot {
+    pub name: String,
+    pub details: String,
+    pub remaining_seconds: u64,
+}
+
+// Estado de la conexión con Discord (ver connection.rs)
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ConnectionSnapshot {
+    pub state: String,
+    // Intentos fallidos seguidos
+    pub attempts: u32,
+    // Segundos hasta el próximo reintento, si hay uno previsto
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_retry_seconds: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatusSnapshot {
+    pub running: bool,
+    pub paused: bool,
+    pub connected: bool,
+    #[serde(default)]
+    pub connection: ConnectionSnapshot,
+    // Perfil activo y todos los disponibles
+    #[serde(default)]
+    pub profile: String,
+    #[serde(default)]
+    pub profiles: Vec<String>,
+    pub app_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_app_id_switch: Option<String>,
+    // config.json no es válido y se sigue con la última configuración buena
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_migration: Option<String>,
+    pub activity_count: usize,
+    pub current_activity: Option<ActivitySnapshot>,
+    // Orden de rotación del perfil, ya descrito
+    #[serde(default)]
+    pub rotation: String,
+    // Actividad fijada con override, si hay una
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub override_activity: Option<TimedActivitySnapshot>,
+    // Anuncio que se está mostrando y cuántos esperan en la cola
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub announcement: Option<TimedActivitySnapshot>,
+    #[serde(default)]
+    pub queued_announcements: usize,
+    // Motivo por el que la presencia está borrada (horas de silencio...)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hidden: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failing_activities: Vec<FailingActivitySnapshot>,
+    // Dónde está la configuración en uso y dónde se guardan logs y estado
+    #[serde(default)]
+    pub config_path: String,
+    #[serde(default)]
+    pub state_dir: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ControlResponse {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<StatusSnapshot>,
+}
+
+impl ControlResponse {
+    fn error(error: String) -> Self {
+        Self {
+            ok: false,
+            message: None,
+            error: Some(error),
+            status: None,
+        }
+    }
+}
+
+#[cfg(unix)]
+fn control_socket_path() -> StdResult<PathBuf, String> {
+    Ok(paths::state_dir().join("control.sock"))
+}
+
+#[cfg(windows)]
+fn control_pipe_name() -> String {
+    // Un pipe por usuario para no mezclar sesiones en la misma máquina, y
+    // por directorio de datos como el socket de Unix: con --data-dir o en
+    // modo portable cada instancia tiene el suyo. Las rutas de Windows no
+    // distinguen mayúsculas
+    let user = env::var("USERNAME").unwrap_or_else(|_| "default".to_string());
+    let state_dir = paths::state_dir().to_string_lossy().to_lowercase();
+    format!(
+        r"\\.\pipe\lorianworkspace-{}-{:016x}",
+        user,
+        fnv1a(state_dir.as_bytes())
+    )
+}
+
+// Hash FNV-1a de 64 bits: el cliente y la app lo tienen que calcular igual
+// aunque sean compilaciones distintas, y el Hasher de std no lo garantiza
+#[cfg(windows)]
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+async fn handle_request(line: &str, context: &AppContext) -> ControlResponse {
+    let command: AppCommand = match serde_json::from_str(line) {
+        Ok(command) => command,
+        Err(e) => return ControlResponse::error(format!("Petición inválida: {}", e)),
+    };
+
+    let result = context.handle_command(command).await;
+    let status = Some(context.status_snapshot().await);
+    match result {
+        Ok(message) => ControlResponse {
+            ok: true,
+            message: Some(message),
+            error: None,
+            status,
+        },
+        Err(error) => ControlResponse {
+            ok: false,
+            message: None,
+            error: Some(error),
+            status,
+        },
+    }
+}
+
+async fn handle_connection<S>(stream: S, context: AppContext)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(e) => {
+                log_error(&format!("⚠️  Error leyendo del socket de control: {}", e));
+                break;
+            }
+        };
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = handle_request(&line, &context).await;
+        let mut payload = serde_json::to_string(&response).unwrap_or_else(|e| {
+            format!(
+                "{{\"ok\":false,\"error\":\"Error serializando respuesta: {}\"}}",
+                e
+            )
+        });
+        payload.push('\n');
+
+        if writer.write_all(payload.as_bytes()).await.is_err() || writer.flush().await.is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(unix)]
+pub async fn run_control_server(context: AppContext) -> StdResult<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+    use tokio::net::UnixListener;
+
+    paths::ensure_state_dir()?;
+    let socket_path = control_socket_path()?;
+
+    // Un socket que quedó de una ejecución anterior impide hacer bind. Con el
+    // lock de instancia única tomado, seguro que no es de otro proceso vivo
+    if socket_path.exists() {
+        let _ = fs::remove_file(&socket_path);
+    }
+
+    let listener = UnixListener::bind(&socket_path)
+        .map_err(|e| format!("Error creando socket {}: {}", socket_path.display(), e))?;
+
+    // Solo el usuario actual puede controlar la app
+    let _ = fs::set_permissions(&socket_path, fs::Permissions::from_mode(0o600));
+
+    log_info(&format!(
+        "🔌 Socket de control escuchando en: {}",
+        socket_path.display()
+    ));
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tokio::spawn(handle_connection(stream, context.clone()));
+            }
+            Err(e) => log_error(&format!("⚠️  Error aceptando conexión de control: {}", e)),
+        }
+    }
+}
+
+#[cfg(windows)]
+pub async fn run_control_server(context: AppContext) -> StdResult<(), String> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let pipe_name = control_pipe_name();
+    let mut server = ServerOptions::new()
+        .first_pipe_instance(true)
+        .create(&pipe_name)
+        .map_err(|e| format!("Error creando named pipe {}: {}", pipe_name, e))?;
+
+    log_info(&format!(
+        "🔌 Socket de control escuchando en: {}",
+        pipe_name
+    ));
+
+    loop {
+        server
+            .connect()
+            .await
+            .map_err(|e| format!("Error esperando conexión en {}: {}", pipe_name, e))?;
+
+        // Crear la siguiente instancia antes de atender a este cliente
+        let connected = server;
+        server = ServerOptions::new()
+            .create(&pipe_name)
+            .map_err(|e| format!("Error creando named pipe {}: {}", pipe_name, e))?;
+
+        tokio::spawn(handle_connection(connected, context.clone()));
+    }
+}
+
+// Envía un comando a la instancia en ejecución y espera su respuesta
+pub async fn send_command(command: &AppCommand) -> StdResult<ControlResponse, String> {
+    #[cfg(unix)]
+    let stream = {
+        let socket_path = control_socket_path()?;
+        tokio::net::UnixStream::connect(&socket_path)
+            .await
+            .map_err(|e| format!("No se pudo conectar a {}: {}", socket_path.display(), e))?
+    };
+    #[cfg(windows)]
+    let stream = {
+        let pipe_name = control_pipe_name();
+        tokio::net::windows::named_pipe::ClientOptions::new()
+            .open(&pipe_name)
+            .map_err(|e| format!("No se pudo conectar a {}: {}", pipe_name, e))?
+    };
+
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut request =
+        serde_json::to_string(command).map_err(|e| format!("Error serializando comando: {}", e))?;
+    request.push('\n');
+    writer
+        .write_all(request.as_bytes())
+        .await
+        .map_err(|e| format!("Error enviando comando: {}", e))?;
+
+    let line = BufReader::new(reader)
+        .lines()
+        .next_line()
+        .await
+        .map_err(|e| format!("Error leyendo respuesta: {}", e))?
+        .ok_or_else(|| "La instancia cerró la conexión sin responder".to_string())?;
+
+    serde_json::from_str(&line).map_err(|e| format!("Respuesta inválida: {}", e))
+}
+
+// Borra el socket al cerrar la app (los named pipes desaparecen solos)
+pub fn cleanup() {
+    #[cfg(unix)]
+    if let Ok(socket_path) = control_socket_path() {
+        let _ = fs::remove_file(socket_path);
+    }
+}
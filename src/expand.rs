@@ -0,0 +1,280 @@
+// Variables de entorno dentro de la configuración, para compartir un mismo
+// archivo entre máquinas (dotfiles) con IDs o URLs distintos en cada una:
+//   ${VAR}           valor de VAR; es un error si no está definida
+//   ${VAR:-defecto}  valor de VAR, o "defecto" si no existe o está vacía
+//   $${VAR}          el texto ${VAR} tal cual
+// Se sustituyen en cualquier texto del archivo al cargarlo. Una variable sin
+// valor es un error de validación: nunca se envía "${VAR}" a Discord.
+//
+// Además LORIAN_APP_ID, si está definida, sustituye a discord.app_id.
+
+use super::*;
+use serde_json::Value;
+
+pub const APP_ID_OVERRIDE: &str = "LORIAN_APP_ID";
+
+// Un texto con una variable que no se pudo sustituir
+pub struct ExpansionError {
+    // Ruta del campo, con el mismo formato que los diagnósticos
+    pub field: String,
+    pub message: String,
+}
+
+// De dónde salen los valores de las variables: el entorno del proceso, o
+// uno inventado en las pruebas (sin tocar el entorno, que es de todos)
+type Lookup<'a> = &'a dyn Fn(&str) -> Option<String>;
+
+fn env_lookup(name: &str) -> Option<String> {
+    env::var(name).ok()
+}
+
+// Sustituye las variables en todos los textos de value
+pub fn expand_value(value: &mut Value) -> Vec<ExpansionError> {
+    expand_value_with(value, &env_lookup)
+}
+
+fn expand_value_with(value: &mut Value, lookup: Lookup) -> Vec<ExpansionError> {
+    let mut errors = Vec::new();
+    expand_at(value, String::new(), lookup, &mut errors);
+    errors
+}
+
+fn expand_at(value: &mut Value, path: String, lookup: Lookup, errors: &mut Vec<ExpansionError>) {
+    match value {
+        Value::String(text) => match expand_str(text, lookup) {
+            Ok(expanded) => *text = expanded,
+            Err(message) => errors.push(ExpansionError {
+                field: path,
+                message,
+            }),
+        },
+        Value::Array(items) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                expand_at(item, format!("{}[{}]", path, index), lookup, errors);
+            }
+        }
+        Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                let child = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                expand_at(item, child, lookup, errors);
+            }
+        }
+        _ => {}
+    }
+}
+
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn expand_str(text: &str, lookup: Lookup) -> StdResult<String, String> {
+    if !text.contains("${") {
+        return Ok(text.to_string());
+    }
+
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        // $${ es un ${ literal
+        if rest[..start].ends_with('$') {
+            result.push_str(&rest[..start - 1]);
+            result.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        result.push_str(&rest[..start]);
+
+        let after = &rest[start + 2..];
+        let Some(end) = after.find('}') else {
+            return Err(format!("falta la '}}' de cierre en '{}'", text));
+        };
+        let expression = &after[..end];
+        let (name, default) = match expression.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (expression, None),
+        };
+        if !is_valid_name(name) {
+            return Err(format!(
+                "'${{{}}}' no es un nombre de variable válido",
+                expression
+            ));
+        }
+
+        match (lookup(name), default) {
+            (Some(value), Some(default)) if value.is_empty() => result.push_str(default),
+            (Some(value), _) => result.push_str(&value),
+            (None, Some(default)) => result.push_str(default),
+            (None, None) => {
+                return Err(format!(
+                    "la variable de entorno {} no está definida (usa ${{{}:-valor}} para dar un valor por defecto)",
+                    name, name
+                ))
+            }
+        }
+        rest = &after[end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+// Aplica LORIAN_APP_ID sobre discord.app_id. Solo toca el archivo principal
+// (los de actividades no tienen sección discord)
+pub fn apply_overrides(value: &mut Value) {
+    apply_overrides_with(value, &env_lookup);
+}
+
+fn apply_overrides_with(value: &mut Value, lookup: Lookup) {
+    let Some(app_id) = app_id_override_with(lookup) else {
+        return;
+    };
+    if let Some(discord) = value.get_mut("discord").and_then(Value::as_object_mut) {
+        discord.insert("app_id".to_string(), Value::String(app_id));
+    }
+}
+
+pub fn app_id_override() -> Option<String> {
+    app_id_override_with(&env_lookup)
+}
+
+fn app_id_override_with(lookup: Lookup) -> Option<String> {
+    lookup(APP_ID_OVERRIDE)
+        .map(|app_id| app_id.trim().to_string())
+        .filter(|app_id| !app_id.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // Entorno de prueba: LORIAN_EXPAND_TEST y LORIAN_EXPAND_EMPTY definidas
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "LORIAN_EXPAND_TEST" => Some("valor".to_string()),
+            "LORIAN_EXPAND_EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    fn expand(text: &str) -> StdResult<String, String> {
+        expand_str(text, &lookup)
+    }
+
+    #[test]
+    fn expands_defined_variables() {
+        assert_eq!(
+            expand("a ${LORIAN_EXPAND_TEST} b"),
+            Ok("a valor b".to_string())
+        );
+        assert_eq!(
+            expand("${LORIAN_EXPAND_TEST}${LORIAN_EXPAND_TEST}"),
+            Ok("valorvalor".to_string())
+        );
+        assert_eq!(
+            expand("${LORIAN_EXPAND_TEST:-otro}"),
+            Ok("valor".to_string())
+        );
+    }
+
+    #[test]
+    fn missing_variable_is_an_error() {
+        let error = expand("id ${LORIAN_EXPAND_MISSING}").unwrap_err();
+        assert!(
+            error.contains("LORIAN_EXPAND_MISSING no está definida"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn defaults() {
+        assert_eq!(
+            expand("${LORIAN_EXPAND_MISSING:-por defecto}"),
+            Ok("por defecto".to_string())
+        );
+        assert_eq!(expand("${LORIAN_EXPAND_MISSING:-}"), Ok(String::new()));
+        // Una variable vacía también usa el valor por defecto
+        assert_eq!(expand("${LORIAN_EXPAND_EMPTY:-x}"), Ok("x".to_string()));
+        assert_eq!(expand("${LORIAN_EXPAND_EMPTY}"), Ok(String::new()));
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            expand("$${LORIAN_EXPAND_MISSING}"),
+            Ok("${LORIAN_EXPAND_MISSING}".to_string())
+        );
+        // Solo cuenta "${": un $ suelto o $$ sin llave se quedan
+        assert_eq!(
+            expand("cuesta $5 o $$10"),
+            Ok("cuesta $5 o $$10".to_string())
+        );
+        assert_eq!(expand("{profile}"), Ok("{profile}".to_string()));
+    }
+
+    #[test]
+    fn unclosed_expression() {
+        let error = expand("${LORIAN_EXPAND_TEST").unwrap_err();
+        assert!(error.contains("falta la '}'"), "{}", error);
+        assert!(expand("a ${").is_err());
+    }
+
+    #[test]
+    fn invalid_names() {
+        for text in ["${}", "${1ABC}", "${A-B}", "${ A }"] {
+            assert!(expand(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn expand_value_reports_field_paths() {
+        let mut value = json!({
+            "discord": {
+                "activities": [
+                    {
+                        "details": "${LORIAN_EXPAND_MISSING:-ok}",
+                        "state": "${LORIAN_EXPAND_MISSING}"
+                    }
+                ],
+                "duration": 5
+            }
+        });
+        let errors = expand_value_with(&mut value, &lookup);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "discord.activities[0].state");
+        assert_eq!(value["discord"]["activities"][0]["details"], "ok");
+        assert_eq!(value["discord"]["duration"], 5);
+    }
+
+    #[test]
+    fn app_id_override_takes_precedence() {
+        let config = || json!({ "discord": { "app_id": "111111111111111111" } });
+        let with_override = |app_id: &'static str| {
+            move |name: &str| (name == APP_ID_OVERRIDE).then(|| app_id.to_string())
+        };
+
+        let mut value = config();
+        apply_overrides_with(&mut value, &with_override(" 222222222222222222 "));
+        assert_eq!(value["discord"]["app_id"], "222222222222222222");
+        // Vacía no cuenta
+        let mut value = config();
+        apply_overrides_with(&mut value, &with_override(""));
+        assert_eq!(value["discord"]["app_id"], "111111111111111111");
+
+        let mut value = config();
+        apply_overrides_with(&mut value, &lookup);
+        assert_eq!(value["discord"]["app_id"], "111111111111111111");
+        // Los archivos de actividades no tienen sección discord
+        let mut value = json!({ "activities": [] });
+        apply_overrides_with(&mut value, &with_override("222222222222222222"));
+        assert_eq!(value, json!({ "activities": [] }));
+    }
+}
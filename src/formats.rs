@@ -0,0 +1,262 @@
+// Formatos del archivo de configuración. Además de config.json se aceptan
+// config.toml, config.yaml/config.yml y config.json5 (JSON con comentarios y
+// comas finales), que son más cómodos para listas largas de actividades.
+//
+// Todos se leen a un serde_json::Value y a partir de ahí el resto de la app
+// (migraciones, validación, Config) no sabe de qué formato venía.
+
+use super::*;
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Json,
+    Json5,
+    Toml,
+    Yaml,
+}
+
+// Archivos que se buscan en el directorio de datos, por orden de prioridad:
+// si existen varios se usa el primero. config.json va el último porque es el
+// que la app crea sola la primera vez; cualquier otro lo ha creado el usuario.
+pub const CONFIG_FILE_NAMES: &[&str] = &[
+    "config.toml",
+    "config.yaml",
+    "config.yml",
+    "config.json5",
+    "config.json",
+];
+
+// El que se crea cuando no hay ninguno
+pub const DEFAULT_CONFIG_FILE: &str = "config.json";
+
+pub struct ParseError {
+    pub message: String,
+    // Línea y columna (empezando en 1)
+    pub location: Option<(usize, usize)>,
+}
+
+impl ConfigFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(Self::from_name)
+    }
+
+    // Acepta la extensión o el nombre del formato (para `convert <formato>`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "json5" => Some(Self::Json5),
+            "toml" => Some(Self::Toml),
+            "yaml" | "yml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Json5 => "json5",
+            Self::Toml => "toml",
+            Self::Yaml => "yaml",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Json => "JSON",
+            Self::Json5 => "JSON5",
+            Self::Toml => "TOML",
+            Self::Yaml => "YAML",
+        }
+    }
+
+    pub fn parse(self, source: &str) -> StdResult<Value, ParseError> {
+        match self {
+            Self::Json => serde_json::from_str(source).map_err(|e| ParseError {
+                message: strip_location(&e.to_string()),
+                location: Some((e.line(), e.column())),
+            }),
+            Self::Json5 => json5::from_str(source).map_err(|e| ParseError {
+                message: strip_location(&e.to_string()),
+                location: e
+                    .position()
+                    .map(|position| (position.line + 1, position.column + 1)),
+            }),
+            Self::Toml => toml::from_str(source).map_err(|e| ParseError {
+                message: e.message().to_string(),
+                location: e.span().map(|span| offset_to_location(source, span.start)),
+            }),
+            Self::Yaml => serde_yaml::from_str(source).map_err(|e| ParseError {
+                message: strip_location(&e.to_string()),
+                location: e.location().map(|l| (l.line(), l.column())),
+            }),
+        }
+    }
+
+    pub fn serialize<T: Serialize>(self, value: &T) -> StdResult<String, String> {
+        match self {
+            // No hay serializador "bonito" de JSON5; JSON indentado es JSON5 válido
+            Self::Json | Self::Json5 => {
+                serde_json::to_string_pretty(value).map_err(|e| e.to_string())
+            }
+            Self::Toml => toml::to_string_pretty(value).map_err(|e| e.to_string()),
+            Self::Yaml => serde_yaml::to_string(value).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+// Los mensajes de serde acaban en " at line X column Y" (ya va en la posición)
+fn strip_location(message: &str) -> String {
+    match message.rfind(" at line ") {
+        Some(index) => message[..index].to_string(),
+        None => message.to_string(),
+    }
+}
+
+fn offset_to_location(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .map(|last| last.chars().count())
+        .unwrap_or(0)
+        + 1;
+    (line, column)
+}
+
+pub fn is_config_file_name(name: &str) -> bool {
+    CONFIG_FILE_NAMES.contains(&name)
+}
+
+// El archivo de configuración en uso dentro de dir, según la prioridad
+pub fn find_config_file(dir: &Path) -> Option<PathBuf> {
+    CONFIG_FILE_NAMES
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+// Otros archivos de configuración presentes que se ignoran por la prioridad
+pub fn shadowed_config_files(dir: &Path) -> Vec<PathBuf> {
+    CONFIG_FILE_NAMES
+        .iter()
+        .map(|name| dir.join(name))
+        .filter(|path| path.is_file())
+        .skip(1)
+        .collect()
+}
+
+// Archivo de configuración en uso si existe: el de --config o el primero
+// según la prioridad en el directorio de configuración
+pub fn existing_config_file() -> Option<PathBuf> {
+    match paths::config_file_override() {
+        Some(file) => file.is_file().then_some(file),
+        None => find_config_file(&paths::config_dir()),
+    }
+}
+
+// Ruta del archivo de configuración en uso (o del que se crearía)
+pub fn active_config_path() -> StdResult<PathBuf, String> {
+    Ok(existing_config_file()
+        .or_else(paths::config_file_override)
+        .unwrap_or_else(|| paths::config_dir().join(DEFAULT_CONFIG_FILE)))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+// Subcomando `convert`: reescribe la configuración en otro formato junto al
+// original. Devuelve el código de salida del proceso.
+pub fn run_convert(target: ConfigFormat, source: Option<PathBuf>, force: bool) -> i32 {
+    cli::attach_console();
+
+    let source_path = match source.map(Ok).unwrap_or_else(active_config_path) {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            return 1;
+        }
+    };
+    let source_format = ConfigFormat::from_path(&source_path).unwrap_or(ConfigFormat::Json);
+    if source_format == target {
+        eprintln!(
+            "❌ {} ya está en formato {}",
+            source_path.display(),
+            target.name()
+        );
+        return 1;
+    }
+
+    // Solo se convierte una configuración válida: lo que no entiende Config
+    // se perdería por el camino
+    let diagnostics = match validate::check_config_file(&source_path, true) {
+        Ok((_, diagnostics)) => diagnostics,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            return 1;
+        }
+    };
+    // Se convierte solo el archivo principal; los incluidos se quedan como
+    // están. Las variables ${VAR} se conservan sin sustituir
+    let config = fs::read_to_string(&source_path)
+        .ok()
+        .and_then(|content| validate::check_config_source(&content, source_format, false).0);
+    let config = match config {
+        Some(config) if !diagnostics.iter().any(|d| d.is_error()) => config,
+        _ => {
+            for diagnostic in diagnostics.iter().filter(|d| d.is_error()) {
+                eprintln!("❌ {}", diagnostic.display_with_file(&source_path));
+            }
+            eprintln!("❌ Corrige los errores antes de convertir");
+            return 1;
+        }
+    };
+
+    let target_path = source_path.with_extension(target.extension());
+    if target_path.exists() && !force {
+        eprintln!(
+            "❌ {} ya existe (usa --force para sobrescribirlo)",
+            target_path.display()
+        );
+        return 1;
+    }
+
+    let output = match target.serialize(&config) {
+        Ok(output) => output,
+        Err(e) => {
+            eprintln!("❌ Error generando {}: {}", target.name(), e);
+            return 1;
+        }
+    };
+    if let Err(e) = fs::write(&target_path, output) {
+        eprintln!("❌ Error escribiendo {}: {}", target_path.display(), e);
+        return 1;
+    }
+    println!("✅ {} → {}", source_path.display(), target_path.display());
+
+    // Avisar de cuál se va a usar a partir de ahora según la prioridad
+    if let Some(dir) = target_path.parent() {
+        if is_config_file_name(&file_name(&target_path)) {
+            match find_config_file(dir) {
+                Some(active) if active == target_path => println!(
+                    "📁 A partir de ahora se usa {}; {} queda ignorado y puedes borrarlo",
+                    file_name(&target_path),
+                    file_name(&source_path)
+                ),
+                Some(active) => println!(
+                    "⚠️  {} tiene prioridad sobre {}; bórralo para usar el nuevo archivo",
+                    file_name(&active),
+                    file_name(&target_path)
+                ),
+                None => {}
+            }
+        }
+    }
+    0
+}
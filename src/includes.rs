@@ -0,0 +1,126 @@
+// Actividades repartidas en varios archivos. Además de las de
+// discord.activities, la configuración puede listar archivos o carpetas en
+// "include", y siempre se leen los archivos de activities.d/ junto a ella.
+// Cada archivo incluido solo tiene una lista "activities".
+//
+// Orden de la rotación (determinista, no depende del sistema de archivos):
+//   1. discord.activities del archivo principal
+//   2. cada entrada de "include" en el orden de la lista (las carpetas, por
+//      nombre de archivo)
+//   3. activities.d/, por nombre de archivo
+// Un archivo al que se llega dos veces solo se lee la primera.
+
+use super::*;
+use std::collections::HashSet;
+use std::path::Component;
+
+// Carpeta que se incluye siempre, junto al archivo de configuración
+pub const ACTIVITIES_DIR: &str = "activities.d";
+
+// Contenido de un archivo incluido
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ActivitiesFile {
+    pub activities: Vec<ActivityConfig>,
+}
+
+// Una entrada de "include" que no se pudo resolver
+pub struct IncludeError {
+    pub index: usize,
+    pub message: String,
+}
+
+// Archivos de actividades de una carpeta, ordenados por nombre. Se saltan
+// los ocultos y los que no tienen una extensión de configuración (backups
+// del editor, temporales...)
+fn directory_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            let hidden = path
+                .file_name()
+                .map(|name| name.to_string_lossy().starts_with('.'))
+                .unwrap_or(true);
+            !hidden && formats::ConfigFormat::from_path(path).is_some()
+        })
+        .collect();
+    files.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+    files
+}
+
+// Archivos a incluir para la configuración en config_path, en orden
+pub fn resolve(config_path: &Path, include: &[String]) -> (Vec<PathBuf>, Vec<IncludeError>) {
+    let base = config_path.parent().unwrap_or(Path::new("."));
+    let mut files = Vec::new();
+    let mut errors = Vec::new();
+
+    for (index, entry) in include.iter().enumerate() {
+        // join con una ruta absoluta la devuelve tal cual
+        let path = base.join(entry);
+        if path.is_dir() {
+            files.extend(directory_files(&path));
+        } else if path.is_file() {
+            files.push(path);
+        } else {
+            errors.push(IncludeError {
+                index,
+                message: format!("no existe {}", path.display()),
+            });
+        }
+    }
+    files.extend(directory_files(&base.join(ACTIVITIES_DIR)));
+
+    let mut seen = HashSet::new();
+    files.retain(|path| seen.insert(fs::canonicalize(path).unwrap_or_else(|_| path.clone())));
+    (files, errors)
+}
+
+// Lista "include" del archivo principal sin validarlo entero, para que el
+// file watcher sepa qué vigilar aunque la configuración tenga errores
+pub fn include_entries(config_path: &Path) -> Vec<String> {
+    let format =
+        formats::ConfigFormat::from_path(config_path).unwrap_or(formats::ConfigFormat::Json);
+    fs::read_to_string(config_path)
+        .ok()
+        .and_then(|source| format.parse(&source).ok())
+        .and_then(|value| value.get("include").cloned())
+        .map(|mut include| {
+            // Las rutas también pueden llevar ${VAR}
+            expand::expand_value(&mut include);
+            include
+        })
+        .and_then(|include| serde_json::from_value::<Vec<String>>(include).ok())
+        .unwrap_or_default()
+}
+
+// Quita . y .. sin tocar el disco, para que las rutas coincidan con las que
+// informa el file watcher (que tampoco resuelve enlaces)
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push("..");
+                }
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+// Rutas cuyos cambios deben recargar la configuración además del propio
+// archivo principal y de activities.d: los archivos y carpetas de "include"
+pub fn watched_paths(config_path: &Path) -> Vec<PathBuf> {
+    let base = config_path.parent().unwrap_or(Path::new("."));
+    include_entries(config_path)
+        .iter()
+        .map(|entry| normalize(&base.join(entry)))
+        .collect()
+}
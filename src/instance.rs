@@ -0,0 +1,88 @@
+// Bloqueo de instancia única: dos procesos con el mismo app_id se pisan la
+// actividad de Discord cada pocos segundos, así que solo uno puede correr.
+//
+// El lock es un bloqueo exclusivo del sistema operativo sobre
+// lorianworkspace.lock en el directorio de datos. Lo libera el sistema
+// cuando el proceso termina, aunque sea por un crash, así que nunca queda
+// un lock "huérfano" como pasaría con solo comprobar si el archivo existe.
+
+use super::*;
+use std::fs::{File, TryLockError};
+use std::io::{Read, Seek, SeekFrom};
+
+pub struct InstanceLock {
+    // Mantener el archivo abierto mantiene el bloqueo
+    _file: File,
+}
+
+pub enum LockResult {
+    Acquired(InstanceLock),
+    // PID de la instancia en ejecución, si se pudo leer
+    AlreadyRunning(Option<u32>),
+}
+
+fn lock_file_path() -> StdResult<PathBuf, String> {
+    Ok(paths::ensure_state_dir()?.join("lorianworkspace.lock"))
+}
+
+pub fn acquire() -> StdResult<LockResult, String> {
+    let lock_path = lock_file_path()?;
+    let mut file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&lock_path)
+        .map_err(|e| format!("Error abriendo {}: {}", lock_path.display(), e))?;
+
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            let mut content = String::new();
+            let _ = file.read_to_string(&mut content);
+            return Ok(LockResult::AlreadyRunning(content.trim().parse().ok()));
+        }
+        Err(TryLockError::Error(e)) => {
+            return Err(format!("Error bloqueando {}: {}", lock_path.display(), e));
+        }
+    }
+
+    // Guardar el PID para poder decir qué proceso tiene el lock
+    let _ = file.set_len(0);
+    let _ = file.seek(SeekFrom::Start(0));
+    let _ = write!(file, "{}", std::process::id());
+    let _ = file.flush();
+
+    Ok(LockResult::Acquired(InstanceLock { _file: file }))
+}
+
+// Avisa de que ya hay una instancia. En Windows no hay consola ni salida del
+// log, así que se muestra un cuadro de diálogo para que el doble click no
+// parezca ignorado
+pub fn notify_already_running(pid: Option<u32>) {
+    let message = match pid {
+        Some(pid) => format!(
+            "Lorian Workspace ya se está ejecutando (PID {}).\n\
+             Usa el icono del system tray o `lorianworkspace status` para controlarlo.",
+            pid
+        ),
+        None => "Lorian Workspace ya se está ejecutando.\n\
+                 Usa el icono del system tray o `lorianworkspace status` para controlarlo."
+            .to_string(),
+    };
+    log_error(&format!(
+        "⚠️  Otra instancia ya está en ejecución - cerrando esta ({})",
+        message.replace('\n', " ")
+    ));
+
+    #[cfg(windows)]
+    unsafe {
+        let text: Vec<u16> = message.encode_utf16().chain(std::iter::once(0)).collect();
+        let _ = MessageBoxW(
+            None,
+            PCWSTR(text.as_ptr()),
+            w!("Lorian Workspace"),
+            MB_OK | MB_ICONINFORMATION,
+        );
+    }
+}
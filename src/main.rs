@@ -6,12 +6,13 @@ use notify::{
     Config as NotifyConfig, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::fs;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::Mutex;
 
 #[cfg(windows)]
@@ -28,42 +29,254 @@ use windows::{
 // Alias para evitar conflictos con windows::core::Result
 type StdResult<T, E> = std::result::Result<T, E>;
 
-#[derive(Debug, Deserialize, Serialize)]
+mod announcements;
+mod cli;
+mod connection;
+mod control;
+mod expand;
+mod formats;
+mod includes;
+mod instance;
+mod migrate;
+mod overrides;
+mod paths;
+mod presence;
+mod profiles;
+mod ratelimit;
+mod rotation;
+mod schedule;
+mod setup;
+mod templates;
+mod timestamps;
+mod validate;
+mod watcher;
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
 struct ButtonConfig {
     label: String,
     url: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+// Cómo aparece la actividad en el perfil ("Jugando a", "Escuchando"...)
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ActivityKind {
+    Playing,
+    Listening,
+    Watching,
+    Competing,
+}
+
+// Qué texto se muestra junto al nombre en la lista de miembros
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum StatusDisplay {
+    Name,
+    State,
+    Details,
+}
+
+// Desde cuándo cuenta el tiempo "transcurrido" de la actividad, o hasta
+// cuándo la cuenta atrás (ver timestamps.rs)
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum TimestampMode {
+    None,
+    AppStart,
+    ProfileSwitch,
+    ActivityFirstShown,
+    // Fecha/hora fija
+    Start(String),
+    // Cuenta atrás: fecha/hora fija o relativa ("in 25m")
+    End(String),
+}
+
+// Orden de rotación de las actividades (ver rotation.rs)
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum RotationMode {
+    #[default]
+    Sequential,
+    Reverse,
+    PingPong,
+    Shuffle,
+    Weighted,
+    // Nombres de actividades en el orden en que se muestran
+    Playlist(Vec<String>),
+}
+
+// Días, franjas horarias y fechas en los que vale algo (ver schedule.rs).
+// Sin campos desconocidos: un error de escritura haría que el horario se
+// aceptara vacío, sin restringir nada
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct ScheduleConfig {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    days: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    hours: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    from: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    until: Option<String>,
+}
+
+// Horario de una actividad: el nombre de uno de "schedules" o uno propio.
+// Deserialize está en schedule.rs para dar errores útiles
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+enum ScheduleRef {
+    Named(String),
+    Inline(ScheduleConfig),
+}
+
+// Grupo ("2 de 5"): Discord lo muestra junto al state
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+struct PartyConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    current: u32,
+    max: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
 struct ActivityConfig {
     name: String,
     details: String,
     state: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     large_image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     large_text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     small_image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     small_text: Option<String>,
     duration_seconds: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
     buttons: Option<Vec<ButtonConfig>>,
+    // Campos opcionales de la presencia (ver presence.rs)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    activity_type: Option<ActivityKind>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    party: Option<PartyConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instance: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status_display_type: Option<StatusDisplay>,
+    // Enlaces al pulsar cada texto o imagen
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    state_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    large_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    small_url: Option<String>,
+    // Si no se indica se usa el de la sección (perfil)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timestamps: Option<TimestampMode>,
+    // Probabilidad relativa con la rotación "weighted"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    weight: Option<u32>,
+    // Solo entra en la rotación dentro de este horario
+    #[serde(skip_serializing_if = "Option::is_none")]
+    schedule: Option<ScheduleRef>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct ReconnectConfig {
+    #[serde(default = "default_initial_delay_seconds")]
+    initial_delay_seconds: u64,
+    #[serde(default = "default_max_delay_seconds")]
+    max_delay_seconds: u64,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+fn default_initial_delay_seconds() -> u64 {
+    connection::DEFAULT_INITIAL_DELAY_SECONDS
+}
+
+fn default_max_delay_seconds() -> u64 {
+    connection::DEFAULT_MAX_DELAY_SECONDS
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 struct DiscordConfig {
     app_id: String,
+    // Modo de tiempo de las actividades que no indican el suyo
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timestamps: Option<TimestampMode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rotation: Option<RotationMode>,
+    // Horarios con nombre que pueden compartir varias actividades
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    schedules: BTreeMap<String, ScheduleConfig>,
+    // Mientras se cumple no se muestra ninguna actividad
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    quiet_hours: Option<ScheduleConfig>,
+    // Esperas entre reintentos de conexión (ver connection.rs)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reconnect: Option<ReconnectConfig>,
     activities: Vec<ActivityConfig>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 struct Config {
+    // Versión del esquema; los archivos sin ella son anteriores al versionado
+    #[serde(default)]
+    version: u32,
+    // Archivos o carpetas con más actividades (ver includes.rs)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    include: Vec<String>,
+    // Perfil con el que arrancar si no hay uno guardado (ver profiles.rs)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default_profile: Option<String>,
+    // Perfil principal ("default")
     discord: DiscordConfig,
+    // Perfiles con nombre además del principal
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    profiles: BTreeMap<String, DiscordConfig>,
 }
 
-#[derive(Debug, Clone)]
+// Actividad de un override: una de la configuración o una completa (ver
+// overrides.rs, donde está su Deserialize)
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+enum OverrideActivity {
+    Named(String),
+    Inline(Box<ActivityConfig>),
+}
+
+// También es el formato de las peticiones del socket de control:
+// {"command": "next_activity"}
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "command", content = "args", rename_all = "snake_case")]
 enum AppCommand {
     Pause,
     Resume,
+    // Pausa o reanuda según cómo esté (menú del tray)
+    TogglePause,
     NextActivity,
+    SelectActivity(String),
     ReloadConfig,
+    // Cambia al perfil con ese nombre
+    SwitchProfile(String),
+    // Muestra una actividad durante duration segundos y vuelve a la rotación
+    Override {
+        activity: OverrideActivity,
+        duration: u64,
+    },
+    CancelOverride,
+    // Pone en cola una actividad de una sola vez (ver announcements.rs); sin
+    // duration se muestra announcements::DEFAULT_DURATION_SECONDS
+    Announce {
+        activity: OverrideActivity,
+        #[serde(default)]
+        duration: Option<u64>,
+    },
+    ClearAnnouncements,
+    #[serde(alias = "status")]
     ShowStatus,
     ToggleConsole,
     OpenConfig,
@@ -74,59 +287,302 @@ enum AppCommand {
 struct AppState {
     is_paused: bool,
     is_running: bool,
+    // Configuración aplicada actualmente, para comparar en cada recarga
+    config: Config,
+    // Motivo por el que config.json no se pudo aplicar y se sigue con una
+    // configuración anterior; se limpia al recargar un archivo válido
+    config_error: Option<String>,
+    // Resumen de la última migración de esquema aplicada a config.json
+    last_migration: Option<String>,
+    // Perfil en uso (ver profiles.rs)
+    active_profile: String,
 }
 
 #[derive(Debug)]
 struct DiscordPresenceManager {
-    client: Option<DiscordIpcClient>,
+    // Estado de la conexión y el cliente IPC (ver connection.rs)
+    connection: connection::Connection,
     app_id: String,
     activities: Vec<ActivityConfig>,
     current_activity_index: usize,
-    is_connected: bool,
-    last_connection_attempt: SystemTime,
+    // Último cambio de Application ID aplicado en caliente ("viejo → nuevo")
+    last_app_id_switch: Option<String>,
+    // Para las plantillas de los textos (ver templates.rs)
+    profile: String,
+    started_at: Instant,
+    // Contadores de tiempo de la presencia (ver timestamps.rs)
+    clock: timestamps::Clock,
+    default_timestamps: Option<TimestampMode>,
+    // Orden de rotación del perfil (ver rotation.rs)
+    rotation: rotation::Rotation,
+    rotation_mode: RotationMode,
+    // Horarios de la sección (ver schedule.rs)
+    schedules: BTreeMap<String, ScheduleConfig>,
+    quiet_hours: Option<ScheduleConfig>,
+    // Motivo por el que la presencia está borrada ahora mismo
+    hidden: Option<String>,
+    // Actividad fijada temporalmente (ver overrides.rs)
+    active_override: Option<overrides::TimedActivity>,
+    // Anuncios en cola y el que se muestra (ver announcements.rs)
+    announcements: announcements::Announcements,
+    // Límite de envíos de Discord (ver ratelimit.rs)
+    updates: ratelimit::UpdateScheduler,
+    // Actividades que Discord rechazó, con la configuración que tenían y el
+    // motivo; no rotan hasta que cambien en la configuración
+    failing: HashMap<String, (ActivityConfig, presence::PresenceError)>,
 }
 
 impl DiscordPresenceManager {
-    fn new(app_id: &str, activities: Vec<ActivityConfig>) -> Self {
-        Self {
-            client: None,
-            app_id: app_id.to_string(),
-            activities,
+    fn new(config: &DiscordConfig, profile: &str) -> Self {
+        let mut manager = Self {
+            connection: connection::Connection::new(config.reconnect.as_ref()),
+            app_id: config.app_id.clone(),
+            activities: config.activities.clone(),
             current_activity_index: 0,
-            is_connected: false,
-            last_connection_attempt: SystemTime::UNIX_EPOCH,
+            last_app_id_switch: None,
+            profile: profile.to_string(),
+            started_at: Instant::now(),
+            clock: timestamps::Clock::new(),
+            default_timestamps: config.timestamps.clone(),
+            rotation: rotation::Rotation::new(),
+            rotation_mode: config.rotation.clone().unwrap_or_default(),
+            schedules: config.schedules.clone(),
+            quiet_hours: config.quiet_hours.clone(),
+            hidden: None,
+            active_override: None,
+            announcements: announcements::Announcements::default(),
+            updates: ratelimit::UpdateScheduler::default(),
+            failing: HashMap::new(),
+        };
+        manager.restart_rotation();
+        manager
+    }
+
+    // Ajustes de la sección del perfil que no son actividades
+    fn apply_settings(&mut self, config: &DiscordConfig) {
+        self.default_timestamps = config.timestamps.clone();
+        self.schedules = config.schedules.clone();
+        self.quiet_hours = config.quiet_hours.clone();
+        self.connection.apply_settings(config.reconnect.as_ref());
+        let rotation_mode = config.rotation.clone().unwrap_or_default();
+        if rotation_mode != self.rotation_mode {
+            self.rotation_mode = rotation_mode;
+            self.rotation.reset();
         }
     }
 
-    async fn connect(&mut self) -> StdResult<(), String> {
-        self.last_connection_attempt = SystemTime::now();
+    // Vuelve a la primera actividad según el orden de rotación
+    fn restart_rotation(&mut self) {
+        let available = self.available_activities();
+        self.current_activity_index =
+            self.rotation
+                .first(&self.rotation_mode, &self.activities, &available);
+    }
 
-        let mut client = DiscordIpcClient::new(&self.app_id)
-            .map_err(|e| format!("Error creando cliente Discord: {}", e))?;
-        client
-            .connect()
-            .map_err(|e| format!("Error conectando a Discord: {}", e))?;
+    // Si la actividad está dentro de su horario
+    fn is_scheduled(
+        &self,
+        activity: &ActivityConfig,
+        now: &chrono::DateTime<chrono::Local>,
+    ) -> bool {
+        match &activity.schedule {
+            None => true,
+            Some(ScheduleRef::Inline(schedule)) => schedule::is_active(schedule, now),
+            // Un nombre que no existe no pasa la validación
+            Some(ScheduleRef::Named(name)) => self
+                .schedules
+                .get(name)
+                .is_none_or(|schedule| schedule::is_active(schedule, now)),
+        }
+    }
+
+    // Qué actividades se pueden mostrar ahora mismo
+    fn available_activities(&self) -> Vec<bool> {
+        let now = chrono::Local::now();
+        self.activities
+            .iter()
+            .map(|activity| {
+                self.is_scheduled(activity, &now) && !self.failing.contains_key(&activity.name)
+            })
+            .collect()
+    }
+
+    // Por qué no hay que mostrar nada ahora, si es el caso
+    fn hide_reason(&self, available: &[bool]) -> Option<String> {
+        let now = chrono::Local::now();
+        if self
+            .quiet_hours
+            .as_ref()
+            .is_some_and(|quiet_hours| schedule::is_active(quiet_hours, &now))
+        {
+            return Some("horas de silencio".to_string());
+        }
+        if !available.iter().any(|&available| available) {
+            if self.failing.len() >= self.activities.len() {
+                return Some("Discord rechazó todas las actividades".to_string());
+            }
+            return Some("ninguna actividad está dentro de su horario".to_string());
+        }
+        None
+    }
+
+    // Borra la presencia de Discord una vez al empezar a ocultarla; una
+    // conexión nueva ya empieza sin actividad
+    async fn hide(&mut self, reason: String) -> StdResult<(), presence::PresenceError> {
+        if self.hidden.as_ref() == Some(&reason) {
+            return Ok(());
+        }
+        log_info(&format!("🌙 Presencia oculta: {}", reason));
+        self.hidden = Some(reason);
+        self.clear_activity().await
+    }
+
+    // Las rechazadas por Discord, en el orden de la configuración
+    fn failing_activities(&self) -> Vec<control::FailingActivitySnapshot> {
+        self.activities
+            .iter()
+            .filter_map(|activity| {
+                let (_, error) = self.failing.get(&activity.name)?;
+                let (code, message) = match error {
+                    presence::PresenceError::Rejected { code, message } => (*code, message.clone()),
+                    other => (0, other.to_string()),
+                };
+                Some(control::FailingActivitySnapshot {
+                    name: activity.name.clone(),
+                    code,
+                    message,
+                })
+            })
+            .collect()
+    }
+
+    // Un error que no es de la actividad deja la conexión en espera (o sin
+    // reintentos si es permanente)
+    fn connection_error(&mut self, error: presence::PresenceError) -> presence::PresenceError {
+        if error.is_activity_error() {
+            error
+        } else {
+            // Lo pendiente era para la conexión que se ha perdido
+            self.updates.reset();
+            self.connection.failed(error)
+        }
+    }
+
+    // Discord rechazó la actividad: sale de la rotación hasta que cambie en
+    // la configuración. Un override o un anuncio rechazados se terminan
+    fn mark_failing(&mut self, name: &str, error: &presence::PresenceError) {
+        if self
+            .active_override
+            .as_ref()
+            .is_some_and(|active| active.activity.name == name)
+        {
+            self.active_override = None;
+            log_error(&format!("🚫 Override de '{}' terminado: {}", name, error));
+        } else if self
+            .announcements
+            .current
+            .as_ref()
+            .is_some_and(|current| current.activity.name == name)
+        {
+            self.announcements.current = None;
+            log_error(&format!("🚫 Anuncio '{}' descartado: {}", name, error));
+        } else if let Some(activity) = self
+            .activities
+            .iter()
+            .find(|activity| activity.name == name)
+        {
+            log_error(&format!(
+                "🚫 '{}' queda fuera de la rotación hasta que cambie en la configuración: {}",
+                name, error
+            ));
+            self.failing
+                .insert(name.to_string(), (activity.clone(), error.clone()));
+        }
+    }
+
+    // Cambia el perfil de las plantillas; uno distinto reinicia los contadores
+    fn set_profile(&mut self, name: &str) {
+        if self.profile != name {
+            self.profile = name.to_string();
+            self.clock.profile_switched();
+        }
+    }
+
+    // Rellena las plantillas de un texto de la actividad actual
+    fn render_text(&self, template: &str) -> String {
+        let context = templates::TemplateContext {
+            started_at: self.started_at,
+            activity_index: self.current_activity_index,
+            activity_count: self.activities.len(),
+            profile: &self.profile,
+        };
+        templates::render(template, &context)
+    }
 
+    // Conecta con Discord. Abrir el socket y el handshake se hacen sin el
+    // lock del manager: mientras Discord contesta, status y los comandos
+    // siguen respondiendo y ven connecting/handshaking (ver connection.rs)
+    async fn connect(discord_manager: &Mutex<Self>) -> StdResult<(), presence::PresenceError> {
+        let (attempt, app_id) = {
+            let mut manager = discord_manager.lock().await;
+            if is_placeholder_app_id(&manager.app_id) {
+                return Err(presence::PresenceError::PlaceholderAppId);
+            }
+            let Some(attempt) = manager.connection.start() else {
+                return Err(presence::PresenceError::Unavailable(
+                    "ya hay un intento de conexión en curso".to_string(),
+                ));
+            };
+            (attempt, manager.app_id.clone())
+        };
+
+        let opened = connection::open(&app_id);
+        let client = {
+            let mut manager = discord_manager.lock().await;
+            match opened {
+                Ok(client) => manager.connection.opened(attempt, client)?,
+                Err(e) => return Err(manager.connection.attempt_failed(attempt, e)),
+            }
+        };
+
+        let result = connection::handshake(client, &app_id).await;
+        let mut manager = discord_manager.lock().await;
+        match result {
+            Ok(client) => manager.connection.established(attempt, client)?,
+            Err(e) => return Err(manager.connection.attempt_failed(attempt, e)),
+        }
         log_info("Discord RPC conectado exitosamente!");
-        self.client = Some(client);
-        self.is_connected = true;
+        manager.updates.reset();
         Ok(())
     }
 
-    async fn try_reconnect(&mut self) -> bool {
-        // No intentar reconectar más de una vez cada 5 segundos
-        if let Ok(elapsed) = self.last_connection_attempt.elapsed() {
-            if elapsed.as_secs() < 5 {
+    async fn try_reconnect(discord_manager: &Mutex<Self>) -> bool {
+        {
+            let manager = discord_manager.lock().await;
+            // Con el ID de ejemplo Discord nunca va a aceptar la conexión
+            if is_placeholder_app_id(&manager.app_id) {
                 return false;
             }
-        }
 
-        log_info("🔄 Intentando reconectar a Discord...");
-        match self.connect().await {
+            // Esperar a que pase el tiempo de espera del último fallo
+            if !manager.connection.can_attempt() {
+                return false;
+            }
+
+            match manager.connection.attempts() {
+                0 => log_info("🔄 Intentando reconectar a Discord..."),
+                attempts => log_info(&format!(
+                    "🔄 Intentando reconectar a Discord (reintento {})...",
+                    attempts
+                )),
+            }
+        }
+        match Self::connect(discord_manager).await {
             Ok(_) => {
                 log_info("✅ Reconexión exitosa!");
                 // Intentar restaurar la actividad actual
-                if let Err(e) = self.set_current_activity().await {
+                let mut manager = discord_manager.lock().await;
+                if let Err(e) = manager.set_current_activity().await {
                     log_error(&format!("⚠️  Error restaurando actividad: {}", e));
                 }
                 true
@@ -139,83 +595,200 @@ impl DiscordPresenceManager {
     }
 
     fn is_connection_alive(&self) -> bool {
-        self.is_connected && self.client.is_some()
+        self.connection.is_ready()
     }
 
-    async fn set_current_activity(&mut self) -> StdResult<(), String> {
-        if let Some(client) = &mut self.client {
-            if self.activities.is_empty() {
-                return Ok(());
+    // Termina el override si ya pasó su tiempo. Devuelve true si acaba de
+    // terminar, para volver a la actividad que había sin avanzar
+    fn finish_override(&mut self) -> bool {
+        if !self
+            .active_override
+            .as_ref()
+            .is_some_and(|active| active.is_expired())
+        {
+            return false;
+        }
+        if let Some(active) = self.active_override.take() {
+            log_info(&format!(
+                "⌛ Override de '{}' terminado - la rotación sigue",
+                active.activity.name
+            ));
+        }
+        true
+    }
+
+    fn cancel_override(&mut self) -> bool {
+        match self.active_override.take() {
+            Some(active) => {
+                log_info(&format!(
+                    "📌 Override de '{}' cancelado",
+                    active.activity.name
+                ));
+                true
             }
+            None => false,
+        }
+    }
 
-            let activity_config = &self.activities[self.current_activity_index];
-            let timestamp = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs();
+    // Lo fijado por encima de la rotación: el override y si no, el anuncio
+    fn pinned_activity(&self) -> Option<&overrides::TimedActivity> {
+        self.active_override
+            .as_ref()
+            .or(self.announcements.current.as_ref())
+    }
 
-            // Crear toda la actividad de una sola vez con todos los elementos
-            let mut activity_builder = activity::Activity::new()
-                .details(&activity_config.details)
-                .state(&activity_config.state)
-                .timestamps(
-                    activity::Timestamps::new().start(timestamp as i64),
-                );
+    // Muestra la actividad que toca. Si Discord la rechaza, queda marcada y
+    // se prueba con la siguiente; cada vuelta marca una, así que termina
+    async fn set_current_activity(&mut self) -> StdResult<(), presence::PresenceError> {
+        let mut result = self.show_current_activity().await;
+        for _ in 0..self.activities.len() + 2 {
+            match &result {
+                Err(e) if e.is_activity_error() => result = self.show_current_activity().await,
+                _ => break,
+            }
+        }
+        result
+    }
 
-            // Agregar imágenes si están configuradas
-            if let Some(large_image) = &activity_config.large_image {
-                let mut assets = activity::Assets::new().large_image(large_image);
+    async fn show_current_activity(&mut self) -> StdResult<(), presence::PresenceError> {
+        self.finish_override();
+        self.announcements.finish();
+        // Los anuncios esperan a que termine el override
+        if self.active_override.is_none() {
+            self.announcements.start_next();
+        }
 
-                if let Some(large_text) = &activity_config.large_text {
-                    assets = assets.large_text(large_text);
-                }
+        // Un override o un anuncio se muestran aunque estén fuera de horario
+        if self.connection.is_ready()
+            && !self.activities.is_empty()
+            && self.pinned_activity().is_none()
+        {
+            let available = self.available_activities();
+            if let Some(reason) = self.hide_reason(&available) {
+                return self.hide(reason).await;
+            }
+            if !available[self.current_activity_index] {
+                self.next_activity();
+            }
+        }
+        if self.connection.is_ready() && self.hidden.take().is_some() {
+            log_info("☀️  Presencia visible de nuevo");
+        }
 
-                if let Some(small_image) = &activity_config.small_image {
-                    assets = assets.small_image(small_image);
+        if self.connection.is_ready() {
+            let pinned = match (&self.active_override, &self.announcements.current) {
+                (Some(active), _) => Some((active, "override")),
+                (None, Some(current)) => Some((current, "anuncio")),
+                (None, None) => None,
+            };
+            if self.activities.is_empty() && pinned.is_none() {
+                return Ok(());
+            }
 
-                    if let Some(small_text) = &activity_config.small_text {
-                        assets = assets.small_text(small_text);
-                    }
+            let (activity_config, times, time_description) = match pinned {
+                Some((active, kind)) => (
+                    &active.activity,
+                    (None, Some(active.end)),
+                    format!(
+                        "{}, queda {}",
+                        kind,
+                        overrides::format_remaining(active.remaining())
+                    ),
+                ),
+                None => {
+                    let activity_config = &self.activities[self.current_activity_index];
+                    let mode = activity_config
+                        .timestamps
+                        .clone()
+                        .or_else(|| self.default_timestamps.clone())
+                        .unwrap_or(TimestampMode::AppStart);
+                    let times = self.clock.timestamps(&activity_config.name, &mode);
+                    (activity_config, times, timestamps::describe(&mode))
                 }
+            };
 
-                activity_builder = activity_builder.assets(assets);
-            }
+            // Crear toda la actividad de una sola vez con todos los elementos
+            let context = templates::TemplateContext {
+                started_at: self.started_at,
+                activity_index: self.current_activity_index,
+                activity_count: self.activities.len(),
+                profile: &self.profile,
+            };
+            let activity = presence::build_activity(activity_config, &context, times);
+            let text = |field: &str| activity[field].as_str().unwrap_or_default().to_string();
+            let (details, state) = (text("details"), text("state"));
 
-            // Agregar botones usando la sintaxis exacta del ejemplo funcional
-            let activity = if let Some(buttons) = &activity_config.buttons {
-                log_info(&format!("🔘 Configurando {} botones para la actividad", buttons.len()));
-                for (i, btn) in buttons.iter().enumerate() {
-                    log_info(&format!("   {}. '{}' -> {}", i + 1, btn.label, btn.url));
+            let activity = match self.updates.submit(&activity_config.name, activity) {
+                ratelimit::Submit::Send(activity) => activity,
+                ratelimit::Submit::Unchanged => {
+                    log_info(&format!(
+                        "💤 '{}' ya se está mostrando - no se reenvía",
+                        activity_config.name
+                    ));
+                    return Ok(());
+                }
+                ratelimit::Submit::Deferred(wait) => {
+                    log_info(&format!(
+                        "⏳ Límite de Discord ({} cambios cada {}s): '{}' se enviará en {}s",
+                        ratelimit::MAX_UPDATES,
+                        ratelimit::WINDOW.as_secs(),
+                        activity_config.name,
+                        wait.as_secs_f64().ceil()
+                    ));
+                    return Ok(());
                 }
-                
-                // Usar la sintaxis exacta del ejemplo: .buttons(vec![...])
-                let discord_buttons = buttons
-                    .iter()
-                    .map(|btn| activity::Button::new(&btn.label, &btn.url))
-                    .collect::<Vec<_>>();
-
-                log_info("✅ Aplicando botones usando sintaxis vec![...] como en el ejemplo funcional");
-                activity_builder.buttons(discord_buttons)
-            } else {
-                log_info("❌ No hay botones configurados para esta actividad");
-                activity_builder
             };
 
-            match client.set_activity(activity) {
-                Ok(_) => {
-                    self.is_connected = true;
+            match activity
+                .get("buttons")
+                .and_then(|buttons| buttons.as_array())
+            {
+                Some(buttons) => {
+                    log_info(&format!(
+                        "🔘 Configurando {} botones para la actividad",
+                        buttons.len()
+                    ));
+                    for (i, btn) in buttons.iter().enumerate() {
+                        log_info(&format!(
+                            "   {}. '{}' -> {}",
+                            i + 1,
+                            btn["label"].as_str().unwrap_or_default(),
+                            btn["url"].as_str().unwrap_or_default()
+                        ));
+                    }
+                }
+                None => log_info("❌ No hay botones configurados para esta actividad"),
+            }
+
+            let sent = activity.clone();
+            match self
+                .connection
+                .request(move |client| presence::send_activity(client, sent))
+                .await
+            {
+                Ok(applied) => {
                     log_info(&format!(
                         "🎯 Actividad '{}' activada: {} - {} (por {} segundos)",
-                        activity_config.name,
-                        activity_config.details,
-                        activity_config.state,
-                        activity_config.duration_seconds
+                        activity_config.name, details, state, activity_config.duration_seconds
                     ));
-                    
-                    // Debug: mostrar resumen completo de la actividad enviada
-                    log_info("📋 RESUMEN COMPLETO de actividad enviada a Discord:");
-                    log_info(&format!("   📝 Detalles: {}", activity_config.details));
-                    log_info(&format!("   📊 Estado: {}", activity_config.state));
+                    for change in presence::changed_texts(&activity, &applied) {
+                        log_info(&format!("✂️  Discord lo ha cambiado: {}", change));
+                    }
+                    self.updates.record(Some(activity));
+
+                    // Resumen de lo que Discord ha aceptado
+                    let text =
+                        |field: &str| applied[field].as_str().unwrap_or_default().to_string();
+                    log_info("📋 RESUMEN COMPLETO de la actividad aplicada por Discord:");
+                    log_info(&format!("   📝 Detalles: {}", text("details")));
+                    log_info(&format!("   📊 Estado: {}", text("state")));
+                    if let Some(kind) = activity_config.activity_type {
+                        log_info(&format!("   🎭 Tipo: {:?}", kind));
+                    }
+                    log_info(&format!("   ⏱️  Tiempo: {}", time_description));
+                    if let Some(party) = &activity_config.party {
+                        log_info(&format!("   👥 Grupo: {} de {}", party.current, party.max));
+                    }
                     if let Some(large_image) = &activity_config.large_image {
                         log_info(&format!("   🖼️  Imagen grande: {}", large_image));
                     }
@@ -223,23 +796,72 @@ impl DiscordPresenceManager {
                         log_info(&format!("   🔸 Imagen pequeña: {}", small_image));
                     }
                     if let Some(buttons) = &activity_config.buttons {
-                        log_info(&format!("   🔘 {} botones enviados con sintaxis vec![...]", buttons.len()));
+                        log_info(&format!("   🔘 {} botones enviados", buttons.len()));
                     }
-                    log_info("🚀 Activity completo enviado de una sola vez a Discord!");
+                    log_info("🚀 Discord ha confirmado la actividad");
                 }
                 Err(e) => {
-                    let error_msg = format!("Error enviando actividad a Discord: {}", e);
-                    log_error(&error_msg);
-                    self.is_connected = false;
-                    self.client = None;
-                    return Err(error_msg);
+                    log_error(&format!("Error enviando actividad a Discord: {}", e));
+                    if e.is_activity_error() {
+                        self.updates.record_rejected();
+                        let name = activity_config.name.clone();
+                        self.mark_failing(&name, &e);
+                    }
+                    return Err(self.connection_error(e));
                 }
             }
         }
         Ok(())
     }
 
+    // Envía la actividad que esperaba hueco en el límite de Discord, si ya
+    // lo hay
+    async fn flush_pending_update(&mut self) -> StdResult<(), presence::PresenceError> {
+        if !self.connection.is_ready() {
+            // Sin conexión no hay a quién enviarla; al reconectar se vuelve a
+            // enviar la actividad actual. Si se quedara pendiente, la tarea
+            // de envío la reintentaría sin parar
+            self.updates.reset();
+            return Ok(());
+        }
+        let Some((name, activity)) = self.updates.take_due() else {
+            return Ok(());
+        };
+        let sent = activity.clone();
+        match self
+            .connection
+            .request(move |client| presence::send_activity(client, sent))
+            .await
+        {
+            Ok(applied) => {
+                log_info(&format!(
+                    "📤 Actividad pendiente '{}' confirmada por Discord",
+                    name
+                ));
+                for change in presence::changed_texts(&activity, &applied) {
+                    log_info(&format!("✂️  Discord lo ha cambiado: {}", change));
+                }
+                self.updates.record(Some(activity));
+                Ok(())
+            }
+            Err(e) => {
+                log_error(&format!("Error enviando actividad a Discord: {}", e));
+                if !e.is_activity_error() {
+                    return Err(self.connection_error(e));
+                }
+                // Rechazada: se marca y se muestra otra en su lugar
+                self.updates.record_rejected();
+                self.mark_failing(&name, &e);
+                self.set_current_activity().await
+            }
+        }
+    }
+
     fn get_current_activity_duration(&self) -> u64 {
+        // Con un override o un anuncio, hasta que termine
+        if let Some(active) = self.pinned_activity() {
+            return (active.remaining().as_secs_f64().ceil() as u64).max(1);
+        }
         if self.activities.is_empty() {
             return 30; // valor por defecto
         }
@@ -248,11 +870,37 @@ impl DiscordPresenceManager {
 
     fn next_activity(&mut self) {
         if !self.activities.is_empty() {
-            self.current_activity_index = (self.current_activity_index + 1) % self.activities.len();
+            let available = self.available_activities();
+            self.current_activity_index = self.rotation.next(
+                &self.rotation_mode,
+                self.current_activity_index,
+                &self.activities,
+                &available,
+            );
+        }
+    }
+
+    fn select_activity(&mut self, name: &str) -> StdResult<(), String> {
+        match self.activities.iter().position(|a| a.name == name) {
+            Some(index) if !self.is_scheduled(&self.activities[index], &chrono::Local::now()) => {
+                Err(format!("La actividad '{}' está fuera de su horario", name))
+            }
+            Some(_) if self.failing.contains_key(name) => Err(format!(
+                "La actividad '{}' no se puede mostrar: {}",
+                name, self.failing[name].1
+            )),
+            Some(index) => {
+                self.current_activity_index = index;
+                Ok(())
+            }
+            None => Err(format!("No existe ninguna actividad llamada '{}'", name)),
         }
     }
 
     fn reload_activities(&mut self, new_activities: Vec<ActivityConfig>) {
+        // Las rechazadas vuelven a rotar si han cambiado
+        self.failing
+            .retain(|_, (failed, _)| new_activities.contains(failed));
         self.activities = new_activities;
         if self.current_activity_index >= self.activities.len() {
             self.current_activity_index = 0;
@@ -274,65 +922,198 @@ impl DiscordPresenceManager {
             current.name,
             self.current_activity_index + 1,
             self.activities.len(),
-            current.details
+            self.render_text(&current.details)
         )
     }
 
-    async fn clear_activity(&mut self) -> StdResult<(), String> {
-        if let Some(client) = &mut self.client {
-            client
-                .clear_activity()
-                .map_err(|e| format!("Error limpiando actividad: {}", e))?;
+    async fn clear_activity(&mut self) -> StdResult<(), presence::PresenceError> {
+        if self.connection.is_ready() {
+            if let Err(e) = self.connection.request(presence::clear_activity).await {
+                log_error(&format!("Error limpiando actividad: {}", e));
+                return Err(self.connection_error(e));
+            }
+            self.updates.reset();
+            self.updates.record(None);
             log_info("Actividad de Discord limpiada");
         }
         Ok(())
     }
 
     async fn disconnect(&mut self) {
-        if let Some(mut client) = self.client.take() {
-            let _ = client.close();
-            println!("Discord RPC desconectado");
-        }
-        self.is_connected = false;
-        self.client = None;
+        self.connection.close();
     }
-}
 
-fn get_app_data_dir() -> StdResult<PathBuf, String> {
-    #[cfg(windows)]
-    {
-        if let Ok(appdata) = env::var("APPDATA") {
-            let app_dir = PathBuf::from(appdata).join("lorianworkspace");
-            return Ok(app_dir);
+    // Cambia de aplicación de Discord: limpia la actividad de la aplicación
+    // anterior y cierra su conexión. El bucle de rotación conecta con el
+    // nuevo ID en cuanto se le despierta (rotation_wakeup).
+    async fn switch_app_id(&mut self, new_app_id: &str) {
+        let old_app_id = std::mem::replace(&mut self.app_id, new_app_id.to_string());
+
+        if let Err(e) = self.clear_activity().await {
+            log_error(&format!("⚠️  {}", e));
+        }
+        self.disconnect().await;
+
+        self.last_app_id_switch = Some(format!("{} → {}", old_app_id, new_app_id));
+        log_info(&format!(
+            "🔀 Application ID cambiado: {} → {}",
+            old_app_id, new_app_id
+        ));
+
+        // disconnect deja la conexión lista para intentarlo ya, sin esperas
+        if is_placeholder_app_id(new_app_id) {
+            log_error("⏸️  El nuevo Application ID es el de ejemplo - no se conectará a Discord");
         }
     }
+}
+
+// Diferencias entre la configuración aplicada y la recién cargada
+#[derive(Debug, Default)]
+struct ConfigDiff {
+    app_id: Option<(String, String)>,
+    // Modo de tiempo de la sección (anterior, nuevo)
+    timestamps: Option<(Option<TimestampMode>, Option<TimestampMode>)>,
+    rotation: Option<(Option<RotationMode>, Option<RotationMode>)>,
+    // Esperas entre reintentos de conexión (anterior, nuevo)
+    reconnect: Option<(Option<ReconnectConfig>, Option<ReconnectConfig>)>,
+    // schedules o quiet_hours
+    schedules: bool,
+    added: Vec<String>,
+    removed: Vec<String>,
+    modified: Vec<String>,
+    reordered: bool,
+}
 
-    // Fallback para otros sistemas o si APPDATA no existe
-    if let Ok(home) = env::var("HOME") {
-        let app_dir = PathBuf::from(home).join(".lorianworkspace");
-        return Ok(app_dir);
+impl ConfigDiff {
+    fn is_empty(&self) -> bool {
+        self.app_id.is_none()
+            && self.timestamps.is_none()
+            && self.rotation.is_none()
+            && self.reconnect.is_none()
+            && !self.schedules
+            && self.added.is_empty()
+            && self.removed.is_empty()
+            && self.modified.is_empty()
+            && !self.reordered
     }
 
-    // Último fallback - directorio actual
-    Ok(PathBuf::from("."))
+    fn describe(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some((old, new)) = &self.app_id {
+            lines.push(format!("🔀 Application ID: {} → {}", old, new));
+        }
+        if let Some((_, new)) = &self.timestamps {
+            let mode = new.clone().unwrap_or(TimestampMode::AppStart);
+            lines.push(format!(
+                "⏱️  Tiempo por defecto: {}",
+                timestamps::describe(&mode)
+            ));
+        }
+        if let Some((_, new)) = &self.rotation {
+            let mode = new.clone().unwrap_or_default();
+            lines.push(format!("🔀 Rotación: {}", rotation::describe(&mode)));
+        }
+        if let Some((_, new)) = &self.reconnect {
+            let (initial, max) = new
+                .as_ref()
+                .map(|reconnect| (reconnect.initial_delay_seconds, reconnect.max_delay_seconds))
+                .unwrap_or((
+                    connection::DEFAULT_INITIAL_DELAY_SECONDS,
+                    connection::DEFAULT_MAX_DELAY_SECONDS,
+                ));
+            lines.push(format!(
+                "🔌 Reintentos de conexión: desde {}s hasta {}s",
+                initial, max
+            ));
+        }
+        if self.schedules {
+            lines.push("🕘 Horarios actualizados".to_string());
+        }
+        if !self.added.is_empty() {
+            lines.push(format!("➕ Actividades nuevas: {}", self.added.join(", ")));
+        }
+        if !self.removed.is_empty() {
+            lines.push(format!(
+                "➖ Actividades eliminadas: {}",
+                self.removed.join(", ")
+            ));
+        }
+        if !self.modified.is_empty() {
+            lines.push(format!(
+                "✏️  Actividades modificadas: {}",
+                self.modified.join(", ")
+            ));
+        }
+        if self.reordered {
+            lines.push("🔃 Orden de actividades cambiado".to_string());
+        }
+        lines
+    }
 }
 
-fn ensure_app_data_dir_exists() -> StdResult<PathBuf, String> {
-    let app_dir = get_app_data_dir()?;
+fn diff_config(old: &DiscordConfig, new: &DiscordConfig) -> ConfigDiff {
+    let mut diff = ConfigDiff::default();
 
-    if !app_dir.exists() {
-        fs::create_dir_all(&app_dir)
-            .map_err(|e| format!("Error creando directorio {}: {}", app_dir.display(), e))?;
-        println!("📁 Creado directorio: {}", app_dir.display());
+    if old.app_id != new.app_id {
+        diff.app_id = Some((old.app_id.clone(), new.app_id.clone()));
+    }
+    if old.timestamps != new.timestamps {
+        diff.timestamps = Some((old.timestamps.clone(), new.timestamps.clone()));
+    }
+    if old.rotation != new.rotation {
+        diff.rotation = Some((old.rotation.clone(), new.rotation.clone()));
+    }
+    if old.reconnect != new.reconnect {
+        diff.reconnect = Some((old.reconnect.clone(), new.reconnect.clone()));
+    }
+    diff.schedules = old.schedules != new.schedules || old.quiet_hours != new.quiet_hours;
+
+    // Las actividades se identifican por nombre
+    for activity in &new.activities {
+        match old.activities.iter().find(|a| a.name == activity.name) {
+            None => diff.added.push(activity.name.clone()),
+            Some(previous) if previous != activity => diff.modified.push(activity.name.clone()),
+            Some(_) => {}
+        }
+    }
+    for activity in &old.activities {
+        if !new.activities.iter().any(|a| a.name == activity.name) {
+            diff.removed.push(activity.name.clone());
+        }
     }
 
-    Ok(app_dir)
+    let common_order = |activities: &[ActivityConfig], other: &[ActivityConfig]| {
+        activities
+            .iter()
+            .filter(|a| other.iter().any(|o| o.name == a.name))
+            .map(|a| a.name.clone())
+            .collect::<Vec<_>>()
+    };
+    diff.reordered = common_order(&old.activities, &new.activities)
+        != common_order(&new.activities, &old.activities);
+
+    diff
+}
+
+// Application ID de ejemplo que trae el config.json por defecto
+const PLACEHOLDER_APP_ID: &str = "1234567890123456789";
+
+fn is_placeholder_app_id(app_id: &str) -> bool {
+    app_id.trim() == PLACEHOLDER_APP_ID
 }
 
 fn get_default_config() -> Config {
     Config {
+        version: migrate::CONFIG_VERSION,
+        include: Vec::new(),
+        default_profile: None,
         discord: DiscordConfig {
-            app_id: "1234567890123456789".to_string(), // ⚠️ CAMBIAR ESTE ID
+            app_id: PLACEHOLDER_APP_ID.to_string(), // ⚠️ CAMBIAR ESTE ID
+            timestamps: None,
+            rotation: None,
+            schedules: BTreeMap::new(),
+            quiet_hours: None,
+            reconnect: None,
             activities: vec![
                 ActivityConfig {
                     name: "commission".to_string(),
@@ -342,7 +1123,7 @@ fn get_default_config() -> Config {
                     large_text: Some("Commission Status".to_string()),
                     small_image: Some("thanks".to_string()),
                     small_text: Some("Available now".to_string()),
-                    duration_seconds: 3,
+                    duration_seconds: 5,
                     buttons: Some(vec![
                         ButtonConfig {
                             label: "💰 Commission Info".to_string(),
@@ -353,6 +1134,7 @@ fn get_default_config() -> Config {
                             url: "https://example.com/portfolio".to_string(),
                         },
                     ]),
+                    ..Default::default()
                 },
                 ActivityConfig {
                     name: "working".to_string(),
@@ -367,6 +1149,7 @@ fn get_default_config() -> Config {
                         label: "📞 Contact Me".to_string(),
                         url: "https://example.com/contact".to_string(),
                     }]),
+                    ..Default::default()
                 },
                 ActivityConfig {
                     name: "chatting".to_string(),
@@ -387,6 +1170,7 @@ fn get_default_config() -> Config {
                             url: "https://example.com/services".to_string(),
                         },
                     ]),
+                    ..Default::default()
                 },
                 ActivityConfig {
                     name: "surprised".to_string(),
@@ -396,11 +1180,12 @@ fn get_default_config() -> Config {
                     large_text: Some("Wow!".to_string()),
                     small_image: Some("thanks".to_string()),
                     small_text: Some("Grateful".to_string()),
-                    duration_seconds: 2,
+                    duration_seconds: 4,
                     buttons: Some(vec![ButtonConfig {
                         label: "🖼️ See Latest Work".to_string(),
                         url: "https://example.com/latest".to_string(),
                     }]),
+                    ..Default::default()
                 },
                 ActivityConfig {
                     name: "confused".to_string(),
@@ -410,11 +1195,12 @@ fn get_default_config() -> Config {
                     large_text: Some("What to do next?".to_string()),
                     small_image: Some("talk_3".to_string()),
                     small_text: Some("Brainstorming".to_string()),
-                    duration_seconds: 3,
+                    duration_seconds: 4,
                     buttons: Some(vec![ButtonConfig {
                         label: "💡 Suggest Ideas".to_string(),
                         url: "https://example.com/suggestions".to_string(),
                     }]),
+                    ..Default::default()
                 },
                 ActivityConfig {
                     name: "cute_mode".to_string(),
@@ -435,18 +1221,24 @@ fn get_default_config() -> Config {
                             url: "https://example.com/cute".to_string(),
                         },
                     ]),
+                    ..Default::default()
                 },
             ],
         },
+        profiles: BTreeMap::new(),
     }
 }
 
 fn create_default_config_file(config_path: &Path) -> StdResult<(), String> {
     let default_config = get_default_config();
-    let config_json = serde_json::to_string_pretty(&default_config)
+    // En el formato que pida la extensión (con --config puede no ser .json)
+    let format =
+        formats::ConfigFormat::from_path(config_path).unwrap_or(formats::ConfigFormat::Json);
+    let config_source = format
+        .serialize(&default_config)
         .map_err(|e| format!("Error serializando configuración default: {}", e))?;
 
-    fs::write(config_path, config_json)
+    fs::write(config_path, config_source)
         .map_err(|e| format!("Error escribiendo archivo de configuración: {}", e))?;
 
     log_info(&format!(
@@ -456,11 +1248,68 @@ fn create_default_config_file(config_path: &Path) -> StdResult<(), String> {
     Ok(())
 }
 
+// Copia de la última configuración que cargó sin errores. Si config.json
+// deja de ser válido al arrancar (una errata, un guardado a medias) la app
+// sigue funcionando con esta en vez de cerrarse
+const LAST_GOOD_CONFIG_FILE: &str = "config.last-good.json";
+
+// Lee y valida un archivo de configuración con sus includes, registrando
+// cada diagnóstico
+fn read_config_file(config_path: &Path) -> StdResult<Config, String> {
+    // Validar antes de usar nada: una actividad fuera de los límites de
+    // Discord nunca debe llegar a enviarse
+    let (config, diagnostics) = validate::check_config_file(config_path, true)?;
+    accept_checked_config(config_path, config, diagnostics)
+}
+
+fn accept_checked_config(
+    config_path: &Path,
+    config: Option<Config>,
+    diagnostics: Vec<validate::Diagnostic>,
+) -> StdResult<Config, String> {
+    for diagnostic in &diagnostics {
+        if diagnostic.is_error() {
+            log_error(&format!("❌ {}", diagnostic.display_with_file(config_path)));
+        } else {
+            log_info(&format!(
+                "⚠️  {}",
+                diagnostic.display_with_file(config_path)
+            ));
+        }
+    }
+
+    let error_count = diagnostics.iter().filter(|d| d.is_error()).count();
+    match config {
+        Some(config) if error_count == 0 => Ok(config),
+        _ => Err(format!(
+            "{} tiene {} errores (ejecuta `lorianworkspace validate` para verlos)",
+            config_path.display(),
+            error_count
+        )),
+    }
+}
+
 fn load_config() -> StdResult<Config, String> {
-    let app_dir = ensure_app_data_dir_exists()?;
-    let config_path = app_dir.join("config.json");
+    load_config_with_migration().map(|(config, _)| config)
+}
+
+// Como load_config, pero devuelve también el resumen de la migración de
+// esquema si el archivo era de una versión anterior
+fn load_config_with_migration() -> StdResult<(Config, Option<String>), String> {
+    let app_dir = paths::ensure_config_dir()?;
+
+    // Con --config no hay prioridades: se usa ese archivo
+    if paths::config_file_override().is_none() {
+        for shadowed in formats::shadowed_config_files(&app_dir) {
+            log_info(&format!(
+                "⚠️  Se ignora {}: hay otro archivo de configuración con más prioridad",
+                shadowed.display()
+            ));
+        }
+    }
 
-    // Si no existe el config, crear uno por defecto
+    // Si no existe ningún config, crear uno por defecto
+    let config_path = formats::active_config_path()?;
     if !config_path.exists() {
         log_info("🆕 Primera ejecución - creando configuración default...");
         create_default_config_file(&config_path)?;
@@ -471,59 +1320,81 @@ fn load_config() -> StdResult<Config, String> {
         log_info("💡 Puedes editar este archivo para personalizar tu app");
     }
 
-    let config_content = fs::read_to_string(&config_path)
-        .map_err(|e| format!("Error leyendo {}: {}", config_path.display(), e))?;
-    let config: Config = serde_json::from_str(&config_content)
-        .map_err(|e| format!("Error parseando JSON en {}: {}", config_path.display(), e))?;
+    let migration = migrate::migrate_config_file(&config_path)?.map(|report| {
+        for step in &report.steps {
+            log_info(&format!("   🔧 {}", step));
+        }
+        let summary = report.summary();
+        log_info(&format!("🔧 {}", summary));
+        summary
+    });
 
-    Ok(config)
+    let config = read_config_file(&config_path)?;
+    let (included, _) = includes::resolve(&config_path, &config.include);
+    if !included.is_empty() {
+        log_info(&format!(
+            "📂 Actividades incluidas de {} archivos: {}",
+            included.len(),
+            included
+                .iter()
+                .map(|path| path
+                    .strip_prefix(&app_dir)
+                    .unwrap_or(path)
+                    .display()
+                    .to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    if expand::app_id_override().is_some() {
+        log_info(&format!(
+            "🌱 Application ID tomado de {} en lugar de discord.app_id",
+            expand::APP_ID_OVERRIDE
+        ));
+    }
+    if let Err(e) = save_last_good_config(&config_path) {
+        log_error(&format!(
+            "⚠️  No se pudo guardar la copia de la configuración: {}",
+            e
+        ));
+    }
+    Ok((config, migration))
 }
 
-fn setup_file_watcher(
-    command_sender: Sender<AppCommand>,
-) -> StdResult<RecommendedWatcher, notify::Error> {
-    let mut watcher = RecommendedWatcher::new(
-        move |res: StdResult<Event, notify::Error>| {
-            match res {
-                Ok(event) => {
-                    // Solo procesar eventos de modificación de archivos
-                    if matches!(event.kind, EventKind::Modify(_)) {
-                        for path in event.paths {
-                            if path.file_name().and_then(|n| n.to_str()) == Some("config.json") {
-                                log_info("📁 Detectado cambio en config.json - recargando...");
-                                let _ = command_sender.send(AppCommand::ReloadConfig);
-                                break;
-                            }
-                        }
-                    }
-                }
-                Err(e) => log_error(&format!("⚠️  Error del file watcher: {:?}", e)),
-            }
-        },
-        NotifyConfig::default(),
-    )?;
+// Guarda la configuración que acaba de cargar sin sustituir las variables
+// ${VAR}: los valores que vienen del entorno (IDs, URLs) no deben quedar en
+// claro en la copia. Se sustituyen al leerla
+fn save_last_good_config(config_path: &Path) -> StdResult<(), String> {
+    let (config, _) = validate::check_config_file(config_path, false)?;
+    let config = config.ok_or_else(|| format!("{} no se pudo leer", config_path.display()))?;
+    let state_dir = paths::ensure_state_dir()?;
+    let cache_path = state_dir.join(LAST_GOOD_CONFIG_FILE);
+    // Las actividades incluidas ya van combinadas en la copia
+    let config = Config {
+        include: Vec::new(),
+        ..config
+    };
+    let config_json = serde_json::to_string_pretty(&config)
+        .map_err(|e| format!("Error serializando configuración: {}", e))?;
+
+    // Escribir a un temporal y renombrar para no dejar nunca la copia a medias
+    let temp_path = state_dir.join(format!("{}.tmp", LAST_GOOD_CONFIG_FILE));
+    fs::write(&temp_path, config_json)
+        .map_err(|e| format!("Error escribiendo {}: {}", temp_path.display(), e))?;
+    fs::rename(&temp_path, &cache_path)
+        .map_err(|e| format!("Error guardando {}: {}", cache_path.display(), e))
+}
 
-    // Vigilar el directorio de AppData para cambios en config.json
-    match get_app_data_dir() {
-        Ok(app_dir) => {
-            watcher.watch(&app_dir, RecursiveMode::NonRecursive)?;
-            log_info(&format!(
-                "👁️  File watcher iniciado para: {}",
-                app_dir.join("config.json").display()
-            ));
-        }
-        Err(e) => {
-            log_error(&format!(
-                "⚠️  Error obteniendo directorio de AppData: {}",
-                e
-            ));
-            // Fallback al directorio actual
-            watcher.watch(Path::new("."), RecursiveMode::NonRecursive)?;
-            log_info("👁️  File watcher iniciado en directorio actual");
-        }
+fn load_last_good_config() -> StdResult<Config, String> {
+    let cache_path = paths::state_dir().join(LAST_GOOD_CONFIG_FILE);
+    if !cache_path.exists() {
+        return Err("no hay ninguna configuración válida anterior guardada".to_string());
     }
-
-    Ok(watcher)
+    let content = fs::read_to_string(&cache_path)
+        .map_err(|e| format!("Error leyendo {}: {}", cache_path.display(), e))?;
+    let (config, diagnostics) =
+        validate::check_config_source(&content, formats::ConfigFormat::Json, true);
+    accept_checked_config(&cache_path, config, diagnostics)
 }
 
 // Las funciones de terminal menu fueron removidas ya que la app funciona sin consola
@@ -543,6 +1414,9 @@ mod tray {
     const ID_MENU_TOGGLE_CONSOLE: u32 = 1006;
     const ID_MENU_OPEN_CONFIG: u32 = 1007;
     const ID_MENU_EXIT: u32 = 1008;
+    // Submenú de perfiles: un ID por perfil a partir de este
+    const ID_MENU_PROFILE_BASE: u32 = 1100;
+    const MAX_MENU_PROFILES: u32 = 100;
 
     fn loword(l: u32) -> u16 {
         (l & 0xFFFF) as u16
@@ -552,6 +1426,59 @@ mod tray {
     static mut GLOBAL_COMMAND_SENDER: Option<Sender<AppCommand>> = None;
     static mut GLOBAL_APP_STATE: Option<Arc<Mutex<AppState>>> = None;
 
+    // Perfiles (nombres y el activo) para el submenú. window_proc no puede
+    // esperar al Mutex de tokio del estado, así que se guarda aquí una copia
+    static TRAY_PROFILES: std::sync::Mutex<(Vec<String>, String)> =
+        std::sync::Mutex::new((Vec::new(), String::new()));
+
+    pub fn set_profiles(names: Vec<String>, active: &str) {
+        if let Ok(mut profiles) = TRAY_PROFILES.lock() {
+            *profiles = (names, active.to_string());
+        }
+    }
+
+    fn profile_command(cmd: u32) -> Option<AppCommand> {
+        let index = cmd.checked_sub(ID_MENU_PROFILE_BASE)?;
+        if index >= MAX_MENU_PROFILES {
+            return None;
+        }
+        let profiles = TRAY_PROFILES.lock().ok()?;
+        profiles
+            .0
+            .get(index as usize)
+            .map(|name| AppCommand::SwitchProfile(name.clone()))
+    }
+
+    // Submenú con un elemento por perfil y el activo marcado. Con un solo
+    // perfil no se muestra
+    unsafe fn append_profiles_menu(hmenu: HMENU) {
+        let (names, active) = TRAY_PROFILES
+            .lock()
+            .map(|profiles| profiles.clone())
+            .unwrap_or_default();
+        if names.len() < 2 {
+            return;
+        }
+        let Ok(profiles_menu) = CreatePopupMenu() else {
+            return;
+        };
+        for (index, name) in names.iter().take(MAX_MENU_PROFILES as usize).enumerate() {
+            let flags = if *name == active {
+                MF_STRING | MF_CHECKED
+            } else {
+                MF_STRING
+            };
+            let label: Vec<u16> = name.encode_utf16().chain(std::iter::once(0)).collect();
+            let _ = AppendMenuW(
+                profiles_menu,
+                flags,
+                (ID_MENU_PROFILE_BASE + index as u32) as usize,
+                PCWSTR(label.as_ptr()),
+            );
+        }
+        let _ = AppendMenuW(hmenu, MF_POPUP, profiles_menu.0 as usize, w!("🗂️ Perfil"));
+    }
+
     pub struct SystemTray {
         hwnd: HWND,
         command_sender: Sender<AppCommand>,
@@ -720,7 +1647,7 @@ mod tray {
 
         fn handle_menu_command(&self, cmd: u32) {
             let command = match cmd {
-                ID_MENU_PAUSE => AppCommand::Pause,
+                ID_MENU_PAUSE => AppCommand::TogglePause,
                 ID_MENU_RESUME => AppCommand::Resume,
                 ID_MENU_NEXT => AppCommand::NextActivity,
                 ID_MENU_RELOAD => AppCommand::ReloadConfig,
@@ -728,7 +1655,10 @@ mod tray {
                 ID_MENU_TOGGLE_CONSOLE => AppCommand::ToggleConsole,
                 ID_MENU_OPEN_CONFIG => AppCommand::OpenConfig,
                 ID_MENU_EXIT => AppCommand::Exit,
-                _ => return,
+                _ => match profile_command(cmd) {
+                    Some(command) => command,
+                    None => return,
+                },
             };
 
             let _ = self.command_sender.send(command);
@@ -736,7 +1666,7 @@ mod tray {
 
         fn handle_global_menu_command(cmd: u32) {
             let command = match cmd {
-                ID_MENU_PAUSE => AppCommand::Pause,
+                ID_MENU_PAUSE => AppCommand::TogglePause,
                 ID_MENU_RESUME => AppCommand::Resume,
                 ID_MENU_NEXT => AppCommand::NextActivity,
                 ID_MENU_RELOAD => AppCommand::ReloadConfig,
@@ -744,7 +1674,10 @@ mod tray {
                 ID_MENU_TOGGLE_CONSOLE => AppCommand::ToggleConsole,
                 ID_MENU_OPEN_CONFIG => AppCommand::OpenConfig,
                 ID_MENU_EXIT => AppCommand::Exit,
-                _ => return,
+                _ => match profile_command(cmd) {
+                    Some(command) => command,
+                    None => return,
+                },
             };
 
             unsafe {
@@ -789,6 +1722,7 @@ mod tray {
                                     ID_MENU_NEXT as usize,
                                     w!("⏭️ Siguiente Actividad"),
                                 );
+                                append_profiles_menu(hmenu);
                                 AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null());
                                 AppendMenuW(
                                     hmenu,
@@ -917,10 +1851,15 @@ mod tray {
             Ok(SystemTray)
         }
 
+        #[allow(dead_code)]
         pub fn run_message_loop(&self, _: Arc<Mutex<AppState>>) {
             // No-op para plataformas no Windows
         }
     }
+
+    pub fn set_profiles(_: Vec<String>, _: &str) {
+        // Sin menú de perfiles fuera de Windows
+    }
 }
 
 // Función simplificada que no depende de tray-icon por ahora
@@ -997,8 +1936,7 @@ fn toggle_console() {
 #[cfg(windows)]
 fn open_config_file() {
     use windows::Win32::UI::Shell::*;
-    if let Ok(app_dir) = get_app_data_dir() {
-        let config_path = app_dir.join("config.json");
+    if let Ok(config_path) = formats::active_config_path() {
         if config_path.exists() {
             let path_str = config_path.to_string_lossy();
             let path_wide: Vec<u16> = path_str.encode_utf16().chain(std::iter::once(0)).collect();
@@ -1017,9 +1955,11 @@ fn open_config_file() {
 }
 
 #[cfg(not(windows))]
+#[allow(dead_code)]
 fn hide_console() {}
 
 #[cfg(not(windows))]
+#[allow(dead_code)]
 fn show_console() {}
 
 #[cfg(not(windows))]
@@ -1030,8 +1970,7 @@ fn open_config_file() {}
 
 // Sistema de logging
 fn get_log_file_path() -> StdResult<PathBuf, String> {
-    let app_dir = get_app_data_dir()?;
-    Ok(app_dir.join("app.log"))
+    Ok(paths::state_dir().join("app.log"))
 }
 
 fn log_message(message: &str) {
@@ -1054,7 +1993,10 @@ fn log_message(message: &str) {
             if metadata.len() > 5_242_880 {
                 let _ = fs::remove_file(&log_path);
                 // Escribir mensaje de rotación en el nuevo archivo
-                let rotation_msg = format!("[{}] 🔄 Log rotado - archivo anterior borrado (>5MB)\n", timestamp_str);
+                let rotation_msg = format!(
+                    "[{}] 🔄 Log rotado - archivo anterior borrado (>5MB)\n",
+                    timestamp_str
+                );
                 let _ = fs::write(&log_path, rotation_msg);
             }
         }
@@ -1063,7 +2005,6 @@ fn log_message(message: &str) {
         let _ = fs::OpenOptions::new()
             .create(true)
             .append(true)
-            .write(true)
             .open(log_path)
             .and_then(|mut file| file.write_all(log_entry.as_bytes()));
     }
@@ -1084,18 +2025,592 @@ fn log_info(message: &str) {
     log_message(&format!("INFO: {}", message));
 }
 
+// Estado compartido entre las tareas que pueden ejecutar comandos
+// (system tray, socket de control)
+#[derive(Clone)]
+struct AppContext {
+    discord_manager: Arc<Mutex<DiscordPresenceManager>>,
+    app_state: Arc<Mutex<AppState>>,
+    rotation_wakeup: Arc<tokio::sync::Notify>,
+}
+
+impl AppContext {
+    // Ejecuta un comando y devuelve un mensaje con el resultado
+    async fn handle_command(&self, command: AppCommand) -> StdResult<String, String> {
+        match command {
+            AppCommand::Pause => {
+                let mut state = self.app_state.lock().await;
+                state.is_paused = true;
+                log_info("⏸️  Actividades pausadas");
+                Ok("⏸️  Actividades pausadas".to_string())
+            }
+            AppCommand::TogglePause => {
+                let mut state = self.app_state.lock().await;
+                state.is_paused = !state.is_paused;
+                let message = if state.is_paused {
+                    "⏸️  Actividades pausadas"
+                } else {
+                    "▶️ Actividades reanudadas"
+                };
+                log_info(message);
+                Ok(message.to_string())
+            }
+            AppCommand::Resume => {
+                let mut state = self.app_state.lock().await;
+                state.is_paused = false;
+                log_info("▶️ Actividades reanudadas");
+                Ok("▶️ Actividades reanudadas".to_string())
+            }
+            AppCommand::NextActivity => {
+                let is_connected = self.discord_manager.lock().await.is_connection_alive();
+                if !is_connected {
+                    if DiscordPresenceManager::try_reconnect(&self.discord_manager).await {
+                        log_info("🔗 Reconectado antes de cambiar actividad");
+                    } else {
+                        let error_msg =
+                            "❌ No se puede cambiar actividad - Discord no está conectado";
+                        log_error(error_msg);
+                        return Err(error_msg.to_string());
+                    }
+                }
+
+                let mut manager = self.discord_manager.lock().await;
+
+                // Saltar un anuncio vuelve a la actividad que había
+                manager.cancel_override();
+                if !manager.announcements.skip() {
+                    manager.next_activity();
+                }
+                if let Err(e) = manager.set_current_activity().await {
+                    let error_msg = format!("⚠️  Error cambiando actividad: {}", e);
+                    log_error(&error_msg);
+                    Err(error_msg)
+                } else {
+                    log_info("⏭️ Cambiado a siguiente actividad");
+                    Ok("⏭️ Cambiado a siguiente actividad".to_string())
+                }
+            }
+            AppCommand::SelectActivity(name) => {
+                let mut manager = self.discord_manager.lock().await;
+                if let Err(e) = manager.select_activity(&name) {
+                    let error_msg = format!("❌ {}", e);
+                    log_error(&error_msg);
+                    return Err(error_msg);
+                }
+                manager.cancel_override();
+                manager.announcements.skip();
+
+                if !manager.is_connection_alive() {
+                    let message = format!(
+                        "🎯 Actividad '{}' seleccionada - se mostrará al reconectar con Discord",
+                        name
+                    );
+                    log_info(&message);
+                    return Ok(message);
+                }
+
+                if let Err(e) = manager.set_current_activity().await {
+                    let error_msg = format!("⚠️  Error cambiando actividad: {}", e);
+                    log_error(&error_msg);
+                    Err(error_msg)
+                } else {
+                    let message = format!("🎯 Actividad '{}' seleccionada", name);
+                    log_info(&message);
+                    Ok(message)
+                }
+            }
+            AppCommand::ReloadConfig => {
+                match load_config_with_migration() {
+                    Ok((new_config, migration)) => {
+                        let mut manager = self.discord_manager.lock().await;
+                        let mut state = self.app_state.lock().await;
+
+                        if migration.is_some() {
+                            state.last_migration = migration;
+                        }
+
+                        if state.config_error.take().is_some() {
+                            log_info("✅ config.json vuelve a ser válido");
+                        }
+
+                        // Si el perfil activo se borró del archivo, volver al
+                        // perfil por defecto
+                        let mut active_profile = state.active_profile.clone();
+                        if profiles::profile(&new_config, &active_profile).is_none() {
+                            let fallback = profiles::default_profile(&new_config);
+                            log_error(&format!(
+                                "⚠️  El perfil '{}' ya no existe en la configuración - se usa '{}'",
+                                active_profile, fallback
+                            ));
+                            active_profile = fallback;
+                        }
+                        let old_profile = profiles::profile(&state.config, &state.active_profile)
+                            .cloned()
+                            .unwrap_or_else(|| state.config.discord.clone());
+                        let new_profile = profiles::profile(&new_config, &active_profile)
+                            .cloned()
+                            .unwrap_or_else(|| new_config.discord.clone());
+                        tray::set_profiles(profiles::profile_names(&new_config), &active_profile);
+
+                        // Aunque el perfil activo no cambie, los demás sí pueden
+                        let diff = diff_config(&old_profile, &new_profile);
+                        manager.set_profile(&active_profile);
+                        state.config = new_config;
+                        state.active_profile = active_profile;
+                        if diff.is_empty() {
+                            log_info("🔄 config.json recargado - sin cambios");
+                            return Ok("🔄 Configuración recargada - sin cambios".to_string());
+                        }
+                        for line in diff.describe() {
+                            log_info(&line);
+                        }
+
+                        if let Some((_, new_app_id)) = &diff.app_id {
+                            manager.switch_app_id(new_app_id).await;
+                            self.rotation_wakeup.notify_one();
+                        }
+
+                        manager.apply_settings(&new_profile);
+                        let old_count = manager.activities.len();
+                        manager.reload_activities(new_profile.activities);
+                        let new_count = manager.activities.len();
+
+                        let mut lines = vec![format!(
+                            "🔄 Configuración recargada: {} → {} actividades",
+                            old_count, new_count
+                        )];
+                        lines.extend(diff.describe());
+                        let message = lines.join("\n");
+
+                        // Sin conexión, la actividad se aplicará al reconectar
+                        if !manager.is_connection_alive() {
+                            log_info(&lines[0]);
+                            return Ok(message);
+                        }
+
+                        if let Err(e) = manager.set_current_activity().await {
+                            let error_msg =
+                                format!("⚠️  Error aplicando nueva configuración: {}", e);
+                            log_error(&error_msg);
+                            Err(error_msg)
+                        } else {
+                            log_info(&lines[0]);
+                            log_info("✨ Cambios aplicados automáticamente");
+                            Ok(message)
+                        }
+                    }
+                    Err(e) => {
+                        log_error(&format!("❌ Error recargando configuración: {}", e));
+                        log_error(
+                            "💡 Se mantiene la configuración anterior hasta corregir config.json",
+                        );
+                        self.app_state.lock().await.config_error = Some(e.clone());
+                        Err(format!("Error recargando configuración: {}", e))
+                    }
+                }
+            }
+            AppCommand::SwitchProfile(name) => {
+                let mut manager = self.discord_manager.lock().await;
+                let mut state = self.app_state.lock().await;
+
+                let Some(profile) = profiles::profile(&state.config, &name).cloned() else {
+                    let error_msg = format!(
+                        "❌ No existe el perfil '{}' (disponibles: {})",
+                        name,
+                        profiles::profile_names(&state.config).join(", ")
+                    );
+                    log_error(&error_msg);
+                    return Err(error_msg);
+                };
+                if state.active_profile == name {
+                    let message = format!("🗂️  El perfil '{}' ya está activo", name);
+                    log_info(&message);
+                    return Ok(message);
+                }
+
+                log_info(&format!(
+                    "🗂️  Cambiando de perfil: {} → {}",
+                    state.active_profile, name
+                ));
+                if profile.app_id != manager.app_id {
+                    manager.switch_app_id(&profile.app_id).await;
+                }
+                manager.cancel_override();
+                manager.apply_settings(&profile);
+                manager.reload_activities(profile.activities);
+                manager.restart_rotation();
+                manager.set_profile(&name);
+                state.active_profile = name.clone();
+                tray::set_profiles(profiles::profile_names(&state.config), &name);
+                if let Err(e) = profiles::save_active_profile(&name) {
+                    log_error(&format!("⚠️  No se pudo guardar el perfil activo: {}", e));
+                }
+                self.rotation_wakeup.notify_one();
+
+                let message = format!(
+                    "🗂️  Perfil '{}' activado ({} actividades)",
+                    name,
+                    manager.activities.len()
+                );
+                // Sin conexión, la actividad se aplicará al reconectar
+                if manager.is_connection_alive() {
+                    if let Err(e) = manager.set_current_activity().await {
+                        let error_msg = format!("⚠️  Error aplicando el perfil '{}': {}", name, e);
+                        log_error(&error_msg);
+                        return Err(error_msg);
+                    }
+                }
+                log_info(&message);
+                Ok(message)
+            }
+            AppCommand::Override { activity, duration } => {
+                let mut manager = self.discord_manager.lock().await;
+                if duration == 0 {
+                    let error_msg = "❌ La duración del override debe ser mayor que 0".to_string();
+                    log_error(&error_msg);
+                    return Err(error_msg);
+                }
+                let activity = match overrides::resolve(activity, &manager.activities) {
+                    Ok(activity) => activity,
+                    Err(e) => {
+                        let error_msg = format!("❌ {}", e);
+                        log_error(&error_msg);
+                        return Err(error_msg);
+                    }
+                };
+
+                let duration = std::time::Duration::from_secs(duration);
+                let message = format!(
+                    "📌 Override: '{}' durante {}",
+                    activity.name,
+                    overrides::format_remaining(duration)
+                );
+                manager.active_override = Some(overrides::TimedActivity::new(activity, duration));
+                self.rotation_wakeup.notify_one();
+
+                // Sin conexión, se mostrará al reconectar si aún no ha terminado
+                if !manager.is_connection_alive() {
+                    let message = format!("{} - se mostrará al reconectar con Discord", message);
+                    log_info(&message);
+                    return Ok(message);
+                }
+                if let Err(e) = manager.set_current_activity().await {
+                    let error_msg = format!("⚠️  Error aplicando el override: {}", e);
+                    log_error(&error_msg);
+                    return Err(error_msg);
+                }
+                log_info(&message);
+                Ok(message)
+            }
+            AppCommand::CancelOverride => {
+                let mut manager = self.discord_manager.lock().await;
+                if !manager.cancel_override() {
+                    let message = "📌 No hay ningún override activo".to_string();
+                    log_info(&message);
+                    return Ok(message);
+                }
+                self.rotation_wakeup.notify_one();
+
+                let message = "📌 Override terminado - la rotación sigue".to_string();
+                if manager.is_connection_alive() {
+                    if let Err(e) = manager.set_current_activity().await {
+                        let error_msg = format!("⚠️  Error volviendo a la rotación: {}", e);
+                        log_error(&error_msg);
+                        return Err(error_msg);
+                    }
+                }
+                Ok(message)
+            }
+            AppCommand::Announce { activity, duration } => {
+                let mut manager = self.discord_manager.lock().await;
+                let duration = duration.unwrap_or(announcements::DEFAULT_DURATION_SECONDS);
+                if duration == 0 {
+                    let error_msg = "❌ La duración del anuncio debe ser mayor que 0".to_string();
+                    log_error(&error_msg);
+                    return Err(error_msg);
+                }
+                let activity = match announcements::resolve(activity, &manager.activities) {
+                    Ok(activity) => activity,
+                    Err(e) => {
+                        let error_msg = format!("❌ {}", e);
+                        log_error(&error_msg);
+                        return Err(error_msg);
+                    }
+                };
+
+                let duration = std::time::Duration::from_secs(duration);
+                let message = format!(
+                    "📣 Anuncio '{}' en cola ({})",
+                    activity.name,
+                    overrides::format_remaining(duration)
+                );
+                manager.announcements.push(activity, duration);
+
+                let waiting = if manager.active_override.is_some() {
+                    Some("se mostrará al terminar el override")
+                } else if manager.announcements.current.is_some() {
+                    Some("se mostrará al terminar el anuncio actual")
+                } else if !manager.is_connection_alive() {
+                    Some("se mostrará al reconectar con Discord")
+                } else {
+                    None
+                };
+                if let Some(waiting) = waiting {
+                    let message = format!("{} - {}", message, waiting);
+                    log_info(&message);
+                    return Ok(message);
+                }
+
+                self.rotation_wakeup.notify_one();
+                if let Err(e) = manager.set_current_activity().await {
+                    let error_msg = format!("⚠️  Error mostrando el anuncio: {}", e);
+                    log_error(&error_msg);
+                    return Err(error_msg);
+                }
+                log_info(&message);
+                Ok(message)
+            }
+            AppCommand::ClearAnnouncements => {
+                let mut manager = self.discord_manager.lock().await;
+                let showing = manager.announcements.current.is_some();
+                let count = manager.announcements.clear();
+                let message = format!("📣 {} anuncios descartados", count);
+                log_info(&message);
+                if showing {
+                    self.rotation_wakeup.notify_one();
+                    if manager.is_connection_alive() {
+                        if let Err(e) = manager.set_current_activity().await {
+                            let error_msg = format!("⚠️  Error volviendo a la rotación: {}", e);
+                            log_error(&error_msg);
+                            return Err(error_msg);
+                        }
+                    }
+                }
+                Ok(message)
+            }
+            AppCommand::ShowStatus => {
+                let manager = self.discord_manager.lock().await;
+                let state = self.app_state.lock().await;
+                log_info("📊 === Estado de la Aplicación ===");
+                log_info(&manager.get_status());
+                log_info(&format!(
+                    "⏸️  Rotación: {}",
+                    if state.is_paused { "Pausada" } else { "Activa" }
+                ));
+                log_info(&format!("🔗 Conexión: {}", manager.connection.describe()));
+                if let Some(error) = manager.connection.last_error() {
+                    log_error(&format!("   Último error: {}", error));
+                }
+                for failing in manager.failing_activities() {
+                    log_error(&format!(
+                        "🚫 Rechazada por Discord: {} ({}) {}",
+                        failing.name, failing.code, failing.message
+                    ));
+                }
+                log_info(&format!("🗂️  Perfil: {}", state.active_profile));
+                log_info(&format!("🆔 Application ID: {}", manager.app_id));
+                if let Some(switch) = &manager.last_app_id_switch {
+                    log_info(&format!("🔀 Último cambio de Application ID: {}", switch));
+                }
+                if let Some(migration) = &state.last_migration {
+                    log_info(&format!("🔧 {}", migration));
+                }
+                if let Some(error) = &state.config_error {
+                    log_error(&format!(
+                        "⚠️  Usando la última configuración válida - {}",
+                        error
+                    ));
+                }
+                log_info(&format!(
+                    "📱 App: {}",
+                    if state.is_running {
+                        "🟢 Funcionando"
+                    } else {
+                        "🔴 Cerrando"
+                    }
+                ));
+
+                // Mostrar ubicación del config
+                if let Ok(config_path) = formats::active_config_path() {
+                    log_info(&format!("📁 Config: {}", config_path.display()));
+                }
+                log_info(&format!(
+                    "📂 Logs y estado: {} ({})",
+                    paths::state_dir().display(),
+                    paths::source()
+                ));
+                Ok(manager.get_status())
+            }
+            AppCommand::ToggleConsole => {
+                log_info("💻 Alternando visibilidad de consola...");
+                toggle_console();
+                Ok("💻 Visibilidad de consola alternada".to_string())
+            }
+            AppCommand::OpenConfig => {
+                log_info("📝 Abriendo archivo de configuración...");
+                open_config_file();
+                Ok("📝 Archivo de configuración abierto".to_string())
+            }
+            AppCommand::Exit => {
+                let mut state = self.app_state.lock().await;
+                state.is_running = false;
+                log_info("🛑 Cerrando aplicación...");
+                Ok("🛑 Cerrando aplicación...".to_string())
+            }
+        }
+    }
+
+    // Foto del estado actual para las respuestas del socket de control
+    async fn status_snapshot(&self) -> control::StatusSnapshot {
+        let manager = self.discord_manager.lock().await;
+        let state = self.app_state.lock().await;
+
+        let current_activity =
+            manager
+                .activities
+                .get(manager.current_activity_index)
+                .map(|activity| control::ActivitySnapshot {
+                    name: activity.name.clone(),
+                    index: manager.current_activity_index,
+                    details: manager.render_text(&activity.details),
+                    state: manager.render_text(&activity.state),
+                    duration_seconds: activity.duration_seconds,
+                    error: manager
+                        .failing
+                        .get(&activity.name)
+                        .map(|(_, error)| error.to_string()),
+                });
+
+        control::StatusSnapshot {
+            running: state.is_running,
+            paused: state.is_paused,
+            connected: manager.is_connection_alive(),
+            connection: control::ConnectionSnapshot {
+                state: manager.connection.state().name().to_string(),
+                attempts: manager.connection.attempts(),
+                next_retry_seconds: manager
+                    .connection
+                    .retry_in()
+                    .filter(|_| !manager.is_connection_alive())
+                    .map(|wait| wait.as_secs_f64().ceil() as u64),
+                last_error: manager.connection.last_error().map(str::to_string),
+            },
+            profile: state.active_profile.clone(),
+            profiles: profiles::profile_names(&state.config),
+            app_id: manager.app_id.clone(),
+            last_app_id_switch: manager.last_app_id_switch.clone(),
+            config_error: state.config_error.clone(),
+            last_migration: state.last_migration.clone(),
+            activity_count: manager.activities.len(),
+            current_activity,
+            rotation: rotation::describe(&manager.rotation_mode),
+            override_activity: manager.active_override.as_ref().map(|active| {
+                control::TimedActivitySnapshot {
+                    name: active.activity.name.clone(),
+                    details: manager.render_text(&active.activity.details),
+                    remaining_seconds: active.remaining().as_secs(),
+                }
+            }),
+            announcement: manager.announcements.current.as_ref().map(|current| {
+                control::TimedActivitySnapshot {
+                    name: current.activity.name.clone(),
+                    details: manager.render_text(&current.activity.details),
+                    remaining_seconds: current.remaining().as_secs(),
+                }
+            }),
+            queued_announcements: manager.announcements.queued(),
+            hidden: manager.hidden.clone(),
+            failing_activities: manager.failing_activities(),
+            config_path: formats::active_config_path()
+                .map(|path| path.display().to_string())
+                .unwrap_or_default(),
+            state_dir: paths::state_dir().display().to_string(),
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> StdResult<(), String> {
+    // Subcomandos: si se pide algo a una instancia en ejecución, actuar como
+    // cliente del socket de control y salir
+    let args: Vec<String> = env::args().skip(1).collect();
+    // Las rutas se fijan antes que nada: el log ya las usa
+    let args = match cli::split_path_options(&args) {
+        Ok((overrides, args)) => {
+            paths::init(overrides);
+            args
+        }
+        Err(e) => {
+            cli::attach_console();
+            eprintln!("❌ {}", e);
+            cli::print_usage();
+            std::process::exit(2);
+        }
+    };
+    match cli::parse_args(&args) {
+        Ok(cli::CliAction::Run) => {}
+        Ok(cli::CliAction::Help) => {
+            cli::print_usage();
+            return Ok(());
+        }
+        Ok(cli::CliAction::Setup) => {
+            std::process::exit(setup::run_setup_command());
+        }
+        Ok(cli::CliAction::Validate(path)) => {
+            std::process::exit(validate::run_validate(path));
+        }
+        Ok(cli::CliAction::Convert {
+            format,
+            source,
+            force,
+        }) => {
+            std::process::exit(formats::run_convert(format, source, force));
+        }
+        Ok(cli::CliAction::Send(command)) => {
+            std::process::exit(cli::run_client(command).await);
+        }
+        Err(e) => {
+            cli::attach_console();
+            eprintln!("❌ {}", e);
+            cli::print_usage();
+            std::process::exit(2);
+        }
+    }
+
+    // Solo una instancia puede manejar la presencia de Discord
+    let _instance_lock = match instance::acquire() {
+        Ok(instance::LockResult::Acquired(lock)) => lock,
+        Ok(instance::LockResult::AlreadyRunning(pid)) => {
+            instance::notify_already_running(pid);
+            // Código de error para que scripts y autostart sepan que no arrancó
+            std::process::exit(1);
+        }
+        Err(e) => {
+            log_error(&format!("❌ Error comprobando instancia única: {}", e));
+            return Err(e);
+        }
+    };
+
     log_info("🚀 Iniciando Lorian Workspace...");
     log_info("📦 Iniciando en modo background sin consola");
     log_info("💡 Usa el icono del system tray para controlar la app");
     log_info("");
-    
+
+    log_info(&format!(
+        "📂 Configuración en {} - logs y estado en {} ({})",
+        paths::config_dir().display(),
+        paths::state_dir().display(),
+        paths::source()
+    ));
+    if let Some(notice) = paths::notice() {
+        log_info(notice);
+    }
+
     // Mostrar información sobre el sistema de logs
     if let Ok(log_path) = get_log_file_path() {
         log_info(&format!("📝 Logs guardados en: {}", log_path.display()));
         log_info("🔄 Rotación automática: logs se borran al superar 5MB");
-        
+
         // Mostrar tamaño actual del log si existe
         if let Ok(metadata) = fs::metadata(&log_path) {
             let size_mb = metadata.len() as f64 / 1_048_576.0;
@@ -1103,7 +2618,7 @@ async fn main() -> StdResult<(), String> {
         }
     }
     log_info("");
-    
+
     log_info("🚨 INFORMACIÓN IMPORTANTE SOBRE BOTONES:");
     log_info("   • Discord tiene un BUG: NO puedes ver tus propios botones");
     log_info("   • Los botones SÍ aparecen para otros usuarios que vean tu perfil");
@@ -1113,27 +2628,81 @@ async fn main() -> StdResult<(), String> {
     // Como es una app de Windows sin consola, no necesitamos ocultar nada
 
     // Cargar configuración desde config.json
-    let config = match load_config() {
-        Ok(cfg) => cfg,
+    let mut config_error = None;
+    let mut last_migration = None;
+    let mut config = match load_config_with_migration() {
+        Ok((cfg, migration)) => {
+            last_migration = migration;
+            cfg
+        }
         Err(e) => {
             log_error(&format!("❌ Error cargando config.json: {}", e));
-            log_error(
-                "💡 Asegúrate de que el archivo config.json exista y tenga el formato correcto",
-            );
-            return Err(e.into());
+            // Una errata no debe dejar al usuario sin presencia al iniciar
+            // sesión: seguir con la última configuración válida y recoger el
+            // archivo corregido en el siguiente hot reload
+            match load_last_good_config() {
+                Ok(cfg) => {
+                    log_error(
+                        "♻️  Usando la última configuración válida hasta corregir config.json",
+                    );
+                    config_error = Some(e);
+                    cfg
+                }
+                Err(cache_error) => {
+                    log_error(&format!(
+                        "❌ Sin configuración de respaldo: {}",
+                        cache_error
+                    ));
+                    log_error(
+                        "💡 Asegúrate de que el archivo config.json exista y tenga el formato correcto",
+                    );
+                    return Err(e);
+                }
+            }
         }
     };
 
-    if config.discord.activities.is_empty() {
-        log_error("⚠️  No hay actividades configuradas en config.json");
+    let active_profile = profiles::startup_profile(&config);
+
+    // Primera ejecución típica: config.json recién creado con el ID de ejemplo
+    if active_profile == profiles::MAIN_PROFILE && is_placeholder_app_id(&config.discord.app_id) {
+        log_error("⚠️  config.json usa el Application ID de ejemplo - Discord no mostrará nada");
+        if setup::offer_setup_wizard() {
+            match load_config() {
+                Ok(new_config) => {
+                    config = new_config;
+                    config_error = None;
+                }
+                Err(e) => log_error(&format!("❌ Error cargando la nueva configuración: {}", e)),
+            }
+        }
+    }
+
+    // El asistente no toca los perfiles, así que el activo sigue existiendo
+    let profile = profiles::profile(&config, &active_profile)
+        .cloned()
+        .unwrap_or_else(|| config.discord.clone());
+
+    if profile.activities.is_empty() {
+        log_error(&format!(
+            "⚠️  No hay actividades configuradas en el perfil '{}'",
+            active_profile
+        ));
         return Ok(());
     }
 
+    if !config.profiles.is_empty() {
+        log_info(&format!(
+            "🗂️  Perfil activo: {} (disponibles: {})",
+            active_profile,
+            profiles::profile_names(&config).join(", ")
+        ));
+    }
     log_info(&format!(
         "📋 Cargadas {} actividades desde config.json",
-        config.discord.activities.len()
+        profile.activities.len()
     ));
-    for (i, activity) in config.discord.activities.iter().enumerate() {
+    for (i, activity) in profile.activities.iter().enumerate() {
         log_info(&format!(
             "   {}. {} - {} segundos",
             i + 1,
@@ -1143,8 +2712,7 @@ async fn main() -> StdResult<(), String> {
     }
 
     // Mostrar ubicación del archivo de configuración
-    if let Ok(app_dir) = get_app_data_dir() {
-        let config_path = app_dir.join("config.json");
+    if let Ok(config_path) = formats::active_config_path() {
         log_info(&format!(
             "📁 Archivo de configuración: {}",
             config_path.display()
@@ -1153,15 +2721,31 @@ async fn main() -> StdResult<(), String> {
     }
 
     let discord_manager = Arc::new(Mutex::new(DiscordPresenceManager::new(
-        &config.discord.app_id,
-        config.discord.activities,
+        &profile,
+        &active_profile,
     )));
+    tray::set_profiles(profiles::profile_names(&config), &active_profile);
 
     let app_state = Arc::new(Mutex::new(AppState {
         is_paused: false,
         is_running: true,
+        config: config.clone(),
+        config_error,
+        last_migration,
+        active_profile,
     }));
 
+    // Despierta la rotación para que empiece a contar de nuevo (al cambiar
+    // de perfil la actividad nueva dura lo suyo, no lo que le quedaba a la
+    // anterior)
+    let rotation_wakeup = Arc::new(tokio::sync::Notify::new());
+
+    let context = AppContext {
+        discord_manager: discord_manager.clone(),
+        app_state: app_state.clone(),
+        rotation_wakeup: rotation_wakeup.clone(),
+    };
+
     // Canal de comandos para el system tray
     let (command_sender, command_receiver): (Sender<AppCommand>, Receiver<AppCommand>) =
         unbounded();
@@ -1169,11 +2753,14 @@ async fn main() -> StdResult<(), String> {
     // Configurar aplicación
     setup_app()?;
 
-    // Conectar a Discord
-    {
-        let mut manager = discord_manager.lock().await;
-        match manager.connect().await {
+    // Conectar a Discord (con el ID de ejemplo no tiene sentido intentarlo)
+    if is_placeholder_app_id(&profile.app_id) {
+        log_error("⏸️  Conexión con Discord desactivada hasta configurar un Application ID real");
+        log_error("💡 Ejecuta `lorianworkspace setup` o edita discord.app_id en config.json");
+    } else {
+        match DiscordPresenceManager::connect(&discord_manager).await {
             Ok(_) => {
+                let mut manager = discord_manager.lock().await;
                 if let Err(e) = manager.set_current_activity().await {
                     log_error(&format!("⚠️  Error estableciendo actividad inicial: {}", e));
                 } else {
@@ -1181,7 +2768,7 @@ async fn main() -> StdResult<(), String> {
                 }
             }
             Err(e) => {
-                log_error(&format!("❌ Error conectando a Discord: {}", e));
+                log_error(&format!("❌ {}", e));
                 log_error("💡 Asegúrate de que Discord esté abierto y que tengas un Application ID válido");
                 log_error("📋 Ve al setup_discord.md para instrucciones de configuración");
                 log_error("🖼️  Verifica que hayas subido las imágenes al Discord Developer Portal");
@@ -1199,9 +2786,9 @@ async fn main() -> StdResult<(), String> {
     log_info("💡 Click derecho en el icono para ver opciones");
 
     // Inicializar File Watcher para hot reload
-    let _file_watcher = setup_file_watcher(command_sender.clone())
+    watcher::start_config_watcher(command_sender.clone())
         .map_err(|e| format!("Error iniciando file watcher: {}", e))?;
-    log_info("🔥 Hot reload activado - edita config.json y se recargará automáticamente");
+    log_info("🔥 Hot reload activado - edita la configuración y se recargará automáticamente");
 
     // Ya no necesitamos menú terminal para aplicación de bandeja
     log_info("💻 Aplicación configurada para control via tray icon");
@@ -1214,166 +2801,118 @@ async fn main() -> StdResult<(), String> {
             let (duration, is_paused, is_connected) = {
                 let manager = discord_manager_clone.lock().await;
                 let state = app_state_clone.lock().await;
-                (
-                    manager.get_current_activity_duration(),
-                    state.is_paused,
-                    manager.is_connection_alive(),
-                )
+                let mut duration = manager.get_current_activity_duration();
+                // Con la presencia oculta, mirar los horarios cada minuto
+                if manager.hidden.is_some() {
+                    duration = duration.min(60);
+                }
+                (duration, state.is_paused, manager.is_connection_alive())
             };
 
             // Si no está conectado, intentar reconectar
             if !is_connected {
-                let mut manager = discord_manager_clone.lock().await;
-                if manager.try_reconnect().await {
+                // Soltar el lock antes de esperar para no bloquear los comandos
+                let reconnected =
+                    DiscordPresenceManager::try_reconnect(&discord_manager_clone).await;
+                let retry_in = discord_manager_clone.lock().await.connection.retry_in();
+                if reconnected {
                     log_info("🔗 Reconexión exitosa - continuando rotación");
                 } else {
-                    // Esperar antes de intentar nuevamente
-                    tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+                    // Esperar hasta el próximo reintento; sin reintento
+                    // previsto (ID de ejemplo), volver a mirar de vez en
+                    // cuando. Un cambio de configuración despierta antes
+                    let wait = retry_in
+                        .filter(|wait| !wait.is_zero())
+                        .unwrap_or(tokio::time::Duration::from_secs(10));
+                    tokio::select! {
+                        _ = tokio::time::sleep(wait) => {}
+                        _ = rotation_wakeup.notified() => {}
+                    }
                     continue;
                 }
             }
 
             // Esperar la duración especificada para la actividad actual
-            tokio::time::sleep(tokio::time::Duration::from_secs(duration)).await;
+            tokio::select! {
+                _ = tokio::time::sleep(tokio::time::Duration::from_secs(duration)) => {}
+                _ = rotation_wakeup.notified() => continue,
+            }
 
-            // Solo cambiar actividad si no está pausado y está conectado
-            if !is_paused {
-                let mut manager = discord_manager_clone.lock().await;
-                if manager.is_connection_alive() {
-                    manager.next_activity();
-                    if let Err(_) = manager.set_current_activity().await {
-                        // Si falla al establecer actividad, marcar como desconectado
-                        // El próximo ciclo intentará reconectar
-                        log_error("💔 Perdida conexión con Discord - intentando reconectar...");
+            // Solo cambiar actividad si no está pausado y está conectado. Al
+            // terminar un override o un anuncio se vuelve a la actividad que
+            // había, sin avanzar; los anuncios en cola salen también en pausa
+            let mut manager = discord_manager_clone.lock().await;
+            if manager.is_connection_alive() {
+                let resumed = manager.finish_override() | manager.announcements.finish();
+                let announcing = manager.active_override.is_none()
+                    && (manager.announcements.current.is_some()
+                        || manager.announcements.queued() > 0);
+                if resumed || announcing || (!is_paused && manager.pinned_activity().is_none()) {
+                    if !resumed && !announcing {
+                        manager.next_activity();
+                    }
+                    // Si falla la conexión queda en espera y el próximo ciclo
+                    // intentará reconectar; los errores permanentes ya se
+                    // registraron al marcarlos
+                    if let Err(e) = manager.set_current_activity().await {
+                        if !e.is_permanent() {
+                            log_error("💔 Perdida conexión con Discord - intentando reconectar...");
+                        }
                     }
                 }
             }
         }
     });
 
-    // Manejo de comandos del tray
-    let discord_manager_clone2 = discord_manager.clone();
-    let app_state_clone2 = app_state.clone();
+    // Manejo de comandos del tray. recv() bloquea, así que corre en un hilo
+    // propio para no ocupar un worker de tokio (con un solo núcleo dejaría
+    // sin ejecutar al resto de tareas). No es un spawn_blocking: al salir
+    // con stop desde el socket el hilo sigue esperando en recv(), y el
+    // runtime esperaría por él al cerrarse
+    let command_context = context.clone();
+    let runtime_handle = tokio::runtime::Handle::current();
+    std::thread::spawn(move || {
+        while let Ok(command) = command_receiver.recv() {
+            let is_exit = matches!(command, AppCommand::Exit);
+            // Los mensajes ya se registran en el log dentro de handle_command
+            let _ = runtime_handle.block_on(command_context.handle_command(command));
+            if is_exit {
+                break;
+            }
+        }
+    });
+
+    // Envío de las actualizaciones que esperan hueco en el límite de Discord
+    // (ver ratelimit.rs)
+    let flush_manager = discord_manager.clone();
+    let update_wakeup = discord_manager.lock().await.updates.wakeup();
     tokio::spawn(async move {
         loop {
-            if let Ok(command) = command_receiver.recv() {
-                match command {
-                    AppCommand::Pause => {
-                        let mut state = app_state_clone2.lock().await;
-                        state.is_paused = !state.is_paused;
-                        if state.is_paused {
-                            log_info("⏸️  Actividades pausadas");
-                        } else {
-                            log_info("▶️ Actividades reanudadas");
-                        }
-                    }
-                    AppCommand::Resume => {
-                        let mut state = app_state_clone2.lock().await;
-                        state.is_paused = false;
-                        log_info("▶️ Actividades reanudadas");
-                    }
-                    AppCommand::NextActivity => {
-                        let mut manager = discord_manager_clone2.lock().await;
-                        if !manager.is_connection_alive() {
-                            if manager.try_reconnect().await {
-                                log_info("🔗 Reconectado antes de cambiar actividad");
-                            } else {
-                                log_error(
-                                    "❌ No se puede cambiar actividad - Discord no está conectado",
-                                );
-                                continue;
-                            }
-                        }
-
-                        manager.next_activity();
-                        if let Err(e) = manager.set_current_activity().await {
-                            log_error(&format!("⚠️  Error cambiando actividad: {}", e));
-                        } else {
-                            log_info("⏭️ Cambiado a siguiente actividad");
-                        }
-                    }
-                    AppCommand::ReloadConfig => {
-                        // Pequeño delay para evitar múltiples recargas rápidas
-                        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-
-                        let config_result = load_config();
-                        match config_result {
-                            Ok(new_config) => {
-                                let mut manager = discord_manager_clone2.lock().await;
-                                let old_count = manager.activities.len();
-                                manager.reload_activities(new_config.discord.activities);
-                                let new_count = manager.activities.len();
-
-                                if let Err(e) = manager.set_current_activity().await {
-                                    log_error(&format!(
-                                        "⚠️  Error aplicando nueva configuración: {}",
-                                        e
-                                    ));
-                                } else {
-                                    log_info(&format!(
-                                        "🔄 Configuración recargada: {} → {} actividades",
-                                        old_count, new_count
-                                    ));
-                                    log_info("✨ Cambios aplicados automáticamente");
-                                }
-                            }
-                            Err(e) => {
-                                log_error(&format!("❌ Error recargando configuración: {}", e));
-                                log_error("💡 Verifica la sintaxis del JSON en config.json");
-                            }
-                        }
-                    }
-                    AppCommand::ShowStatus => {
-                        let manager = discord_manager_clone2.lock().await;
-                        let state = app_state_clone2.lock().await;
-                        log_info("📊 === Estado de la Aplicación ===");
-                        log_info(&manager.get_status());
-                        log_info(&format!(
-                            "⏸️  Rotación: {}",
-                            if state.is_paused { "Pausada" } else { "Activa" }
-                        ));
-                        log_info(&format!(
-                            "🔗 Conexión: {}",
-                            if manager.is_connection_alive() {
-                                "✅ Conectado"
-                            } else {
-                                "❌ Desconectado"
-                            }
-                        ));
-                        log_info(&format!(
-                            "📱 App: {}",
-                            if state.is_running {
-                                "🟢 Funcionando"
-                            } else {
-                                "🔴 Cerrando"
-                            }
-                        ));
-
-                        // Mostrar ubicación del config
-                        if let Ok(app_dir) = get_app_data_dir() {
-                            let config_path = app_dir.join("config.json");
-                            log_info(&format!("📁 Config: {}", config_path.display()));
-                        }
-                    }
-                    AppCommand::ToggleConsole => {
-                        log_info("💻 Alternando visibilidad de consola...");
-                        toggle_console();
-                    }
-                    AppCommand::OpenConfig => {
-                        log_info("📝 Abriendo archivo de configuración...");
-                        open_config_file();
-                    }
-                    AppCommand::Exit => {
-                        let mut state = app_state_clone2.lock().await;
-                        state.is_running = false;
-                        log_info("🛑 Cerrando aplicación...");
-                        break;
-                    }
+            let wait = flush_manager.lock().await.updates.pending_wait();
+            match wait {
+                Some(wait) => tokio::time::sleep(wait).await,
+                None => {
+                    update_wakeup.notified().await;
+                    continue;
+                }
+            }
+            let mut manager = flush_manager.lock().await;
+            if let Err(e) = manager.flush_pending_update().await {
+                if !e.is_permanent() {
+                    log_error("💔 Perdida conexión con Discord - intentando reconectar...");
                 }
             }
         }
     });
 
+    // Socket de control local (named pipe en Windows) para scripts y plugins
+    let control_context = context.clone();
+    tokio::spawn(async move {
+        if let Err(e) = control::run_control_server(control_context).await {
+            log_error(&format!("⚠️  Error en el socket de control: {}", e));
+        }
+    });
+
     // Ejecutar el message loop del tray en un hilo separado
     // Message loop del tray se integrará con el main loop
 
@@ -1395,6 +2934,7 @@ async fn main() -> StdResult<(), String> {
     }
 
     log_info("🛑 Limpiando recursos...");
+    control::cleanup();
     let mut manager = discord_manager.lock().await;
     let _ = manager.clear_activity().await;
     manager.disconnect().await;
@@ -0,0 +1,149 @@
+// Versionado del esquema de la configuración. Cada archivo lleva una clave
+// "version"; al cargarlo, si es de una versión anterior se le aplican en
+// orden las migraciones pendientes y se reescribe en su sitio (en el mismo
+// formato), dejando antes una copia con fecha junto al original.
+//
+// Las migraciones trabajan sobre el JSON sin tipar, así pueden renombrar o
+// mover campos que el Config actual ya no entendería.
+
+use super::*;
+use serde_json::{Map, Value};
+
+// Versión del esquema que entiende esta versión de la app
+pub const CONFIG_VERSION: u32 = 1;
+
+struct Migration {
+    // Versión a la que deja el archivo
+    to: u32,
+    description: &'static str,
+    apply: fn(&mut Map<String, Value>),
+}
+
+// Una entrada por versión, en orden. Para cambiar el esquema: añadir aquí la
+// migración y subir CONFIG_VERSION.
+const MIGRATIONS: &[Migration] = &[Migration {
+    to: 1,
+    description: "añadida la clave version",
+    apply: v0_to_v1,
+}];
+
+// v0: archivos anteriores al versionado. El contenido ya es el de v1, solo
+// falta la clave version que pone el pipeline.
+fn v0_to_v1(_config: &mut Map<String, Value>) {}
+
+pub struct MigrationReport {
+    pub file_name: String,
+    pub from: u32,
+    pub to: u32,
+    pub steps: Vec<String>,
+    pub backup_path: PathBuf,
+}
+
+impl MigrationReport {
+    pub fn summary(&self) -> String {
+        format!(
+            "{} migrado de v{} a v{} ({}) - copia del original en {}",
+            self.file_name,
+            self.from,
+            self.to,
+            self.steps.join("; "),
+            self.backup_path.display()
+        )
+    }
+}
+
+fn file_version(config: &Map<String, Value>) -> StdResult<u32, String> {
+    match config.get("version") {
+        None => Ok(0),
+        Some(value) => value
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| format!("'version' debe ser un número entero (es {})", value)),
+    }
+}
+
+// La clave version va la primera para que se vea al abrir el archivo
+fn set_version(config: &mut Map<String, Value>, version: u32) {
+    if let Some(value) = config.get_mut("version") {
+        *value = Value::from(version);
+        return;
+    }
+    let mut versioned = Map::new();
+    versioned.insert("version".to_string(), Value::from(version));
+    versioned.append(config);
+    *config = versioned;
+}
+
+// Aplica las migraciones pendientes. Devuelve la descripción de cada paso.
+fn migrate_value(config: &mut Map<String, Value>) -> StdResult<Vec<String>, String> {
+    let version = file_version(config)?;
+    if version > CONFIG_VERSION {
+        return Err(format!(
+            "la configuración usa la versión {} del esquema y esta versión de Lorian Workspace solo entiende hasta la {} - actualiza la aplicación",
+            version, CONFIG_VERSION
+        ));
+    }
+
+    let mut steps = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|m| m.to > version) {
+        (migration.apply)(config);
+        set_version(config, migration.to);
+        steps.push(format!(
+            "v{} → v{}: {}",
+            migration.to - 1,
+            migration.to,
+            migration.description
+        ));
+    }
+    Ok(steps)
+}
+
+// Migra el archivo en su sitio si es de una versión anterior. Si no se puede
+// leer se deja tal cual: la validación ya informa del error con su línea y
+// columna. Al reescribir se pierden los comentarios (TOML, YAML, JSON5), por
+// eso la copia.
+pub fn migrate_config_file(config_path: &Path) -> StdResult<Option<MigrationReport>, String> {
+    let source = fs::read_to_string(config_path)
+        .map_err(|e| format!("Error leyendo {}: {}", config_path.display(), e))?;
+    let format =
+        formats::ConfigFormat::from_path(config_path).unwrap_or(formats::ConfigFormat::Json);
+    let Ok(Value::Object(mut config)) = format.parse(&source) else {
+        return Ok(None);
+    };
+
+    let from = file_version(&config)?;
+    let steps = migrate_value(&mut config)?;
+    if steps.is_empty() {
+        return Ok(None);
+    }
+
+    let file_name = config_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| formats::DEFAULT_CONFIG_FILE.to_string());
+    let backup_path = config_path.with_file_name(format!(
+        "{}.v{}-{}.bak",
+        file_name,
+        from,
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    fs::copy(config_path, &backup_path)
+        .map_err(|e| format!("Error creando la copia {}: {}", backup_path.display(), e))?;
+
+    let migrated = format
+        .serialize(&Value::Object(config))
+        .map_err(|e| format!("Error serializando configuración migrada: {}", e))?;
+    let temp_path = config_path.with_file_name(format!("{}.migrating", file_name));
+    fs::write(&temp_path, migrated)
+        .map_err(|e| format!("Error escribiendo {}: {}", temp_path.display(), e))?;
+    fs::rename(&temp_path, config_path)
+        .map_err(|e| format!("Error reemplazando {}: {}", config_path.display(), e))?;
+
+    Ok(Some(MigrationReport {
+        file_name,
+        from,
+        to: CONFIG_VERSION,
+        steps,
+        backup_path,
+    }))
+}
@@ -0,0 +1,148 @@
+// Override: muestra una actividad concreta durante un tiempo, con cuenta
+// atrás en Discord, y después la rotación sigue por donde iba. La actividad
+// puede ser una de la configuración (por nombre) o una completa indicada en
+// la propia petición:
+//   {"command": "override", "args": {"activity": "cute_mode", "duration": 600}}
+//   {"command": "override", "args": {"activity": {"details": "En una llamada", "state": "Vuelvo enseguida"}, "duration": 900}}
+//
+// Mientras dura no se aplican horarios ni horas de silencio: es una petición
+// explícita. next, select y un cambio de perfil lo terminan antes de tiempo.
+
+use super::*;
+use serde::de::{self, MapAccess, Visitor};
+use serde_json::Value;
+use std::fmt;
+use std::time::Duration;
+
+// Nombre de las actividades indicadas en la petición que no traen uno
+pub const INLINE_NAME: &str = "override";
+
+// Un texto es el nombre de una actividad y un objeto una actividad completa,
+// en la que name y duration_seconds son opcionales
+impl<'de> Deserialize<'de> for OverrideActivity {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> StdResult<Self, D::Error> {
+        struct OverrideActivityVisitor;
+
+        impl<'de> Visitor<'de> for OverrideActivityVisitor {
+            type Value = OverrideActivity;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("el nombre de una actividad o una actividad")
+            }
+
+            fn visit_str<E: de::Error>(self, name: &str) -> StdResult<OverrideActivity, E> {
+                Ok(OverrideActivity::Named(name.to_string()))
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, map: M) -> StdResult<OverrideActivity, M::Error> {
+                let mut value = Value::deserialize(de::value::MapAccessDeserializer::new(map))?;
+                if let Some(object) = value.as_object_mut() {
+                    object
+                        .entry("name")
+                        .or_insert_with(|| Value::String(INLINE_NAME.to_string()));
+                    // Lo que cuenta es la duración del override
+                    object
+                        .entry("duration_seconds")
+                        .or_insert_with(|| Value::from(1));
+                }
+                serde_json::from_value(value)
+                    .map(|activity| OverrideActivity::Inline(Box::new(activity)))
+                    .map_err(de::Error::custom)
+            }
+        }
+
+        deserializer.deserialize_any(OverrideActivityVisitor)
+    }
+}
+
+// Actividad que se muestra durante un tiempo fijo (un override o un anuncio)
+#[derive(Debug)]
+pub struct TimedActivity {
+    pub activity: ActivityConfig,
+    until: Instant,
+    // Final en segundos Unix, para la cuenta atrás de Discord
+    pub end: i64,
+}
+
+impl TimedActivity {
+    pub fn new(activity: ActivityConfig, duration: Duration) -> Self {
+        let end = SystemTime::now()
+            .checked_add(duration)
+            .and_then(|end| end.duration_since(UNIX_EPOCH).ok())
+            .map(|end| end.as_secs() as i64)
+            .unwrap_or_default();
+        Self {
+            activity,
+            until: Instant::now() + duration,
+            end,
+        }
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.until.saturating_duration_since(Instant::now())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_zero()
+    }
+}
+
+// La actividad a mostrar: la de la configuración con ese nombre, o la de la
+// petición si pasa las mismas comprobaciones que las del archivo
+pub fn resolve(
+    activity: OverrideActivity,
+    activities: &[ActivityConfig],
+) -> StdResult<ActivityConfig, String> {
+    match activity {
+        OverrideActivity::Named(name) => activities
+            .iter()
+            .find(|activity| activity.name == name)
+            .cloned()
+            .ok_or_else(|| format!("No existe ninguna actividad llamada '{}'", name)),
+        OverrideActivity::Inline(activity) => {
+            let errors = validate::check_activity(&activity);
+            if errors.is_empty() {
+                Ok(*activity)
+            } else {
+                Err(format!("La actividad no es válida: {}", errors.join("; ")))
+            }
+        }
+    }
+}
+
+// "override <actividad> <duración>" en la línea de comandos. La actividad
+// puede ser un nombre (con espacios) o un objeto JSON
+pub fn parse_cli(args: &[String]) -> StdResult<AppCommand, String> {
+    let usage = "Uso: override <actividad|{json}> <duración> (p. ej. override cute_mode 10m) u override off";
+    if args.len() == 1 && args[0] == "off" {
+        return Ok(AppCommand::CancelOverride);
+    }
+    let [activity @ .., duration] = args else {
+        return Err(usage.to_string());
+    };
+    if activity.is_empty() {
+        return Err(usage.to_string());
+    }
+    let duration = timestamps::parse_duration(duration)
+        .map_err(|e| format!("Duración no válida '{}': {}. {}", duration, e, usage))?;
+
+    let activity = activity.join(" ");
+    let activity = if activity.trim_start().starts_with('{') {
+        serde_json::from_str(&activity).map_err(|e| format!("Actividad JSON no válida: {}", e))?
+    } else {
+        OverrideActivity::Named(activity)
+    };
+    Ok(AppCommand::Override {
+        activity,
+        duration: duration as u64,
+    })
+}
+
+pub fn format_remaining(remaining: Duration) -> String {
+    let seconds = remaining.as_secs();
+    match (seconds / 3600, seconds / 60 % 60, seconds % 60) {
+        (0, 0, seconds) => format!("{}s", seconds),
+        (0, minutes, seconds) => format!("{}m {}s", minutes, seconds),
+        (hours, minutes, _) => format!("{}h {}m", hours, minutes),
+    }
+}
@@ -0,0 +1,211 @@
+// Dónde viven los archivos de la app. Hay dos directorios:
+//   - configuración: config.json (o .toml...), activities.d/ y los includes
+//   - estado: app.log, state.json, config.last-good.json, el lock de
+//     instancia única y el socket de control
+//
+// Por orden de prioridad:
+//   1. --data-dir <dir>: ambos en ese directorio. --config <archivo> usa ese
+//      archivo de configuración (y su carpeta para activities.d)
+//   2. Modo portable: si junto al ejecutable hay un archivo portable.txt,
+//      todo se guarda junto al ejecutable (útil en un USB)
+//   3. Windows: %APPDATA%\lorianworkspace para ambos
+//   4. Linux/macOS: $XDG_CONFIG_HOME/lorianworkspace (~/.config) y
+//      $XDG_STATE_HOME/lorianworkspace (~/.local/state). Si aún no hay
+//      configuración ahí pero existe el antiguo ~/.lorianworkspace, se sigue
+//      usando ese para no perder nada
+//   5. El directorio actual
+//
+// Se resuelve una sola vez al arrancar (init) y todos los módulos preguntan
+// aquí en vez de calcular rutas por su cuenta.
+
+use super::*;
+use std::sync::OnceLock;
+
+const APP_DIR_NAME: &str = "lorianworkspace";
+// Directorio único de versiones anteriores en Linux/macOS
+#[cfg(not(windows))]
+const LEGACY_DIR_NAME: &str = ".lorianworkspace";
+// Archivo junto al ejecutable que activa el modo portable
+pub const PORTABLE_MARKER: &str = "portable.txt";
+
+// Rutas pedidas por línea de comandos
+#[derive(Debug, Default)]
+pub struct Overrides {
+    pub config_file: Option<PathBuf>,
+    pub data_dir: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+struct AppPaths {
+    config_dir: PathBuf,
+    state_dir: PathBuf,
+    // Archivo de configuración fijado con --config
+    config_file: Option<PathBuf>,
+    // De dónde salen las rutas, para el log y el estado
+    source: String,
+    // Aviso a registrar al arrancar (aquí todavía no se puede usar el log)
+    notice: Option<String>,
+}
+
+static PATHS: OnceLock<AppPaths> = OnceLock::new();
+
+// Fija las rutas según la línea de comandos. Tiene que llamarse antes que
+// cualquier otra cosa que use rutas (el log incluido)
+pub fn init(overrides: Overrides) {
+    let _ = PATHS.set(resolve(overrides));
+}
+
+fn paths() -> &'static AppPaths {
+    PATHS.get_or_init(|| resolve(Overrides::default()))
+}
+
+fn absolute(path: PathBuf) -> PathBuf {
+    if path.is_absolute() {
+        return path;
+    }
+    env::current_dir()
+        .map(|dir| dir.join(&path))
+        .unwrap_or(path)
+}
+
+fn resolve(overrides: Overrides) -> AppPaths {
+    let config_file = overrides.config_file.map(absolute);
+    let mut paths = match overrides.data_dir.map(absolute) {
+        Some(dir) => AppPaths {
+            config_dir: dir.clone(),
+            state_dir: dir,
+            config_file: None,
+            source: "--data-dir".to_string(),
+            notice: None,
+        },
+        None => portable_paths().unwrap_or_else(platform_paths),
+    };
+
+    if let Some(file) = config_file {
+        if let Some(parent) = file.parent() {
+            paths.config_dir = parent.to_path_buf();
+        }
+        paths.config_file = Some(file);
+        paths.source = format!("--config, estado: {}", paths.source);
+    }
+    paths
+}
+
+fn portable_paths() -> Option<AppPaths> {
+    let exe_dir = env::current_exe().ok()?.parent()?.to_path_buf();
+    if !exe_dir.join(PORTABLE_MARKER).is_file() {
+        return None;
+    }
+    Some(AppPaths {
+        config_dir: exe_dir.clone(),
+        state_dir: exe_dir,
+        config_file: None,
+        source: format!("modo portable ({})", PORTABLE_MARKER),
+        notice: None,
+    })
+}
+
+#[cfg(windows)]
+fn platform_paths() -> AppPaths {
+    let app_dir = match env::var("APPDATA") {
+        Ok(appdata) => PathBuf::from(appdata).join(APP_DIR_NAME),
+        Err(_) => return current_dir_paths(),
+    };
+    AppPaths {
+        config_dir: app_dir.clone(),
+        state_dir: app_dir,
+        config_file: None,
+        source: "%APPDATA%".to_string(),
+        notice: None,
+    }
+}
+
+#[cfg(not(windows))]
+fn platform_paths() -> AppPaths {
+    let Some(home) = env::var_os("HOME").map(PathBuf::from) else {
+        return current_dir_paths();
+    };
+
+    // Las variables XDG solo valen si son rutas absolutas
+    let xdg_dir = |variable: &str, fallback: &str| {
+        env::var_os(variable)
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .unwrap_or_else(|| home.join(fallback))
+            .join(APP_DIR_NAME)
+    };
+    let config_dir = xdg_dir("XDG_CONFIG_HOME", ".config");
+    let state_dir = xdg_dir("XDG_STATE_HOME", ".local/state");
+
+    let legacy_dir = home.join(LEGACY_DIR_NAME);
+    if legacy_dir.is_dir() && formats::find_config_file(&config_dir).is_none() {
+        return AppPaths {
+            config_dir: legacy_dir.clone(),
+            state_dir: legacy_dir.clone(),
+            config_file: None,
+            source: format!("~/{}", LEGACY_DIR_NAME),
+            notice: Some(format!(
+                "💡 Usando el directorio antiguo {}; mueve la configuración a {} y los logs/estado a {} para seguir las rutas XDG",
+                legacy_dir.display(),
+                config_dir.display(),
+                state_dir.display()
+            )),
+        };
+    }
+
+    AppPaths {
+        config_dir,
+        state_dir,
+        config_file: None,
+        source: "XDG".to_string(),
+        notice: None,
+    }
+}
+
+fn current_dir_paths() -> AppPaths {
+    AppPaths {
+        config_dir: PathBuf::from("."),
+        state_dir: PathBuf::from("."),
+        config_file: None,
+        source: "directorio actual".to_string(),
+        notice: None,
+    }
+}
+
+pub fn config_dir() -> PathBuf {
+    paths().config_dir.clone()
+}
+
+pub fn state_dir() -> PathBuf {
+    paths().state_dir.clone()
+}
+
+// Archivo de configuración fijado con --config, si lo hay
+pub fn config_file_override() -> Option<PathBuf> {
+    paths().config_file.clone()
+}
+
+pub fn source() -> &'static str {
+    &paths().source
+}
+
+pub fn notice() -> Option<&'static str> {
+    paths().notice.as_deref()
+}
+
+fn ensure_dir(dir: PathBuf) -> StdResult<PathBuf, String> {
+    if !dir.exists() {
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Error creando directorio {}: {}", dir.display(), e))?;
+        println!("📁 Creado directorio: {}", dir.display());
+    }
+    Ok(dir)
+}
+
+pub fn ensure_config_dir() -> StdResult<PathBuf, String> {
+    ensure_dir(config_dir())
+}
+
+pub fn ensure_state_dir() -> StdResult<PathBuf, String> {
+    ensure_dir(state_dir())
+}
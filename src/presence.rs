@@ -0,0 +1,400 @@
+// Construcción del SET_ACTIVITY que se envía a Discord y lectura de sus
+// respuestas.
+//
+// El crate discord-rich-presence no conoce todos los campos que acepta
+// Discord (instance, status_display_type, las URLs de los textos y de las
+// imágenes), así que la actividad se arma con su builder, se pasa a JSON, se
+// completa con el resto y se envía con DiscordIpc::send.
+//
+// El crate tampoco mira lo que contesta Discord: cada comando se envía con
+// un nonce y se lee la respuesta que lo trae, para distinguir un Application
+// ID o una actividad rechazados (no se arreglan reintentando) de una
+// conexión cortada (sí).
+//
+// recv() del crate bloquea sin límite, así que las peticiones pasan por
+// call(): cada una en un hilo propio y esperando como mucho REPLY_TIMEOUT.
+
+use super::*;
+use serde_json::{json, Value};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+// Opcodes del protocolo IPC de Discord
+const OPCODE_HANDSHAKE: u8 = 0;
+const OPCODE_FRAME: u8 = 1;
+const OPCODE_CLOSE: u32 = 2;
+// Código de cierre para un client_id que Discord no conoce
+const CLOSE_INVALID_CLIENT_ID: i64 = 4000;
+// Respuestas de otros comandos o eventos que se aceptan antes de la nuestra
+const MAX_SKIPPED_FRAMES: usize = 16;
+// Tiempo máximo esperando a Discord en cada petición
+pub const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Códigos de error de las respuestas RPC de Discord
+fn error_name(code: i64) -> Option<&'static str> {
+    Some(match code {
+        1000 => "error desconocido",
+        4000 => "payload no válido",
+        4002 => "comando no válido",
+        4004 => "evento no válido",
+        4006 => "sin permisos",
+        4007 => "client_id no válido",
+        4008 => "origen no válido",
+        4009 => "token no válido",
+        4010 => "usuario no válido",
+        _ => return None,
+    })
+}
+
+// Errores de la conexión con Discord
+#[derive(Debug, Clone, PartialEq)]
+pub enum PresenceError {
+    // config.json sigue con el Application ID de ejemplo
+    PlaceholderAppId,
+    // Discord rechazó el Application ID en el handshake
+    InvalidAppId(String),
+    // Discord no está abierto o no se pudo crear el cliente
+    Unavailable(String),
+    // Falló la lectura o escritura: la conexión se cortó
+    Io(String),
+    // Discord cerró la conexión con un código
+    Closed { code: i64, message: String },
+    // Discord contestó con un error a la actividad
+    Rejected { code: i64, message: String },
+}
+
+impl PresenceError {
+    // Reintentar (reconectar o volver a enviar lo mismo) no lo arregla
+    pub fn is_permanent(&self) -> bool {
+        matches!(
+            self,
+            Self::PlaceholderAppId | Self::InvalidAppId(_) | Self::Rejected { .. }
+        )
+    }
+
+    // El problema es la actividad, no la conexión
+    pub fn is_activity_error(&self) -> bool {
+        matches!(self, Self::Rejected { .. })
+    }
+}
+
+impl fmt::Display for PresenceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::PlaceholderAppId => write!(
+                f,
+                "config.json usa el Application ID de ejemplo - ejecuta `lorianworkspace setup`"
+            ),
+            Self::InvalidAppId(message) => {
+                write!(f, "Discord rechazó el Application ID: {}", message)
+            }
+            Self::Unavailable(message) => write!(f, "Error conectando a Discord: {}", message),
+            Self::Io(message) => write!(f, "Se perdió la conexión con Discord: {}", message),
+            Self::Closed { code, message } => {
+                write!(f, "Discord cerró la conexión ({}): {}", code, message)
+            }
+            Self::Rejected { code, message } => match error_name(*code) {
+                Some(name) => write!(
+                    f,
+                    "Discord rechazó la actividad ({}, {}): {}",
+                    code, name, message
+                ),
+                None => write!(f, "Discord rechazó la actividad ({}): {}", code, message),
+            },
+        }
+    }
+}
+
+fn io_error(error: impl fmt::Display) -> PresenceError {
+    PresenceError::Io(error.to_string())
+}
+
+// Error de un frame CLOSE: {"code": 4000, "message": "Invalid Client ID"}
+fn closed(data: &Value) -> PresenceError {
+    let code = data["code"].as_i64().unwrap_or_default();
+    let message = data["message"].as_str().unwrap_or_default().to_string();
+    if code == CLOSE_INVALID_CLIENT_ID {
+        PresenceError::InvalidAppId(message)
+    } else {
+        PresenceError::Closed { code, message }
+    }
+}
+
+impl From<ActivityKind> for activity::ActivityType {
+    fn from(kind: ActivityKind) -> Self {
+        match kind {
+            ActivityKind::Playing => Self::Playing,
+            ActivityKind::Listening => Self::Listening,
+            ActivityKind::Watching => Self::Watching,
+            ActivityKind::Competing => Self::Competing,
+        }
+    }
+}
+
+impl StatusDisplay {
+    // Valores de status_display_type en la API de Discord
+    fn code(self) -> u8 {
+        match self {
+            Self::Name => 0,
+            Self::State => 1,
+            Self::Details => 2,
+        }
+    }
+}
+
+// La actividad con las plantillas ya rellenadas, lista para enviar.
+// timestamps son el inicio y el final del contador en segundos Unix (ver
+// timestamps.rs)
+pub fn build_activity(
+    config: &ActivityConfig,
+    context: &templates::TemplateContext,
+    timestamps: (Option<i64>, Option<i64>),
+) -> Value {
+    // Rellenar las plantillas ({time}, {profile}...) y ajustar a los
+    // límites de Discord por si el resultado se pasa o se queda corto
+    let render = |template: &str| {
+        let text = templates::fit_bytes(
+            templates::render(template, context),
+            validate::MAX_TEXT_BYTES,
+        );
+        if text.chars().count() >= validate::MIN_TEXT_BYTES {
+            return text;
+        }
+        log_info(&format!(
+            "⚠️  '{}' se queda en '{}' al rellenarlo, por debajo del mínimo de Discord ({}) - se completa con espacios invisibles",
+            template,
+            text,
+            validate::MIN_TEXT_BYTES
+        ));
+        templates::pad_chars(text, validate::MIN_TEXT_BYTES)
+    };
+    let details = render(&config.details);
+    let state = render(&config.state);
+    let large_text = config.large_text.as_deref().map(render);
+    let small_text = config.small_text.as_deref().map(render);
+    let labels: Vec<String> = config
+        .buttons
+        .iter()
+        .flatten()
+        .map(|button| {
+            templates::fit_chars(
+                templates::render(&button.label, context),
+                validate::MAX_BUTTON_LABEL_CHARS,
+            )
+        })
+        .collect();
+
+    let mut builder = activity::Activity::new().details(&details).state(&state);
+
+    let (start, end) = timestamps;
+    if start.is_some() || end.is_some() {
+        let mut discord_timestamps = activity::Timestamps::new();
+        if let Some(start) = start {
+            discord_timestamps = discord_timestamps.start(start);
+        }
+        if let Some(end) = end {
+            discord_timestamps = discord_timestamps.end(end);
+        }
+        builder = builder.timestamps(discord_timestamps);
+    }
+
+    // Sin imagen grande no se envían assets
+    if let Some(large_image) = &config.large_image {
+        let mut assets = activity::Assets::new().large_image(large_image);
+        if let Some(large_text) = &large_text {
+            assets = assets.large_text(large_text);
+        }
+        if let Some(small_image) = &config.small_image {
+            assets = assets.small_image(small_image);
+            if let Some(small_text) = &small_text {
+                assets = assets.small_text(small_text);
+            }
+        }
+        builder = builder.assets(assets);
+    }
+
+    if let Some(buttons) = &config.buttons {
+        builder = builder.buttons(
+            buttons
+                .iter()
+                .zip(&labels)
+                .map(|(button, label)| activity::Button::new(label, &button.url))
+                .collect(),
+        );
+    }
+
+    if let Some(kind) = config.activity_type {
+        builder = builder.activity_type(kind.into());
+    }
+
+    if let Some(party) = &config.party {
+        let mut discord_party =
+            activity::Party::new().size([party.current as i32, party.max as i32]);
+        if let Some(id) = &party.id {
+            discord_party = discord_party.id(id);
+        }
+        builder = builder.party(discord_party);
+    }
+
+    let mut activity = serde_json::to_value(builder).unwrap_or_else(|_| json!({}));
+
+    // Campos que el builder no tiene
+    if let Some(object) = activity.as_object_mut() {
+        if let Some(instance) = config.instance {
+            object.insert("instance".to_string(), Value::Bool(instance));
+        }
+        if let Some(display) = config.status_display_type {
+            object.insert(
+                "status_display_type".to_string(),
+                Value::from(display.code()),
+            );
+        }
+        for (field, url) in [
+            ("details_url", &config.details_url),
+            ("state_url", &config.state_url),
+        ] {
+            if let Some(url) = url {
+                object.insert(field.to_string(), Value::String(url.clone()));
+            }
+        }
+        if let Some(assets) = object.get_mut("assets").and_then(Value::as_object_mut) {
+            for (field, url) in [
+                ("large_url", &config.large_url),
+                ("small_url", &config.small_url),
+            ] {
+                if let Some(url) = url {
+                    assets.insert(field.to_string(), Value::String(url.clone()));
+                }
+            }
+        }
+    }
+    activity
+}
+
+// Hace una petición al cliente en un hilo propio, para no ocupar un worker
+// del runtime ni dejar el manager bloqueado si Discord no contesta. No es un
+// spawn_blocking: el runtime esperaría al hilo colgado al cerrarse. Con
+// respuesta devuelve el cliente para seguir usándolo y el resultado; si pasa
+// REPLY_TIMEOUT el cliente se queda en ese hilo (se suelta cuando Discord
+// conteste o cierre) y el error es Io, para que se reconecte
+pub async fn call<T, F>(
+    mut client: DiscordIpcClient,
+    request: F,
+) -> StdResult<(DiscordIpcClient, StdResult<T, PresenceError>), PresenceError>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut DiscordIpcClient) -> StdResult<T, PresenceError> + Send + 'static,
+{
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    std::thread::spawn(move || {
+        let result = request(&mut client);
+        let _ = sender.send((client, result));
+    });
+    match tokio::time::timeout(REPLY_TIMEOUT, receiver).await {
+        Ok(Ok(reply)) => Ok(reply),
+        Ok(Err(_)) => Err(PresenceError::Io(
+            "la petición a Discord se interrumpió".to_string(),
+        )),
+        Err(_) => Err(PresenceError::Io(format!(
+            "Discord no contestó en {}s",
+            REPLY_TIMEOUT.as_secs()
+        ))),
+    }
+}
+
+// Discord contesta al handshake con READY, o cerrando la conexión.
+// Devuelve los datos de READY (versión, usuario...)
+pub fn handshake(client: &mut DiscordIpcClient, app_id: &str) -> StdResult<Value, PresenceError> {
+    client
+        .send(json!({ "v": 1, "client_id": app_id }), OPCODE_HANDSHAKE)
+        .map_err(io_error)?;
+    let (opcode, data) = client.recv().map_err(io_error)?;
+    if opcode == OPCODE_CLOSE {
+        return Err(closed(&data));
+    }
+    Ok(data["data"].clone())
+}
+
+fn next_nonce() -> String {
+    static NONCE: AtomicU64 = AtomicU64::new(0);
+    format!(
+        "lorian-{}-{}",
+        std::process::id(),
+        NONCE.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+// Envía un comando y espera la respuesta con su nonce. Devuelve el campo
+// data de la respuesta
+fn command(
+    client: &mut DiscordIpcClient,
+    cmd: &str,
+    args: Value,
+) -> StdResult<Value, PresenceError> {
+    let nonce = next_nonce();
+    let payload = json!({
+        "cmd": cmd,
+        "args": args,
+        "nonce": nonce,
+    });
+    client.send(payload, OPCODE_FRAME).map_err(io_error)?;
+
+    for _ in 0..MAX_SKIPPED_FRAMES {
+        let (opcode, data) = client.recv().map_err(io_error)?;
+        if opcode == OPCODE_CLOSE {
+            return Err(closed(&data));
+        }
+        if data["nonce"].as_str() != Some(nonce.as_str()) {
+            continue;
+        }
+        if data["evt"].as_str() == Some("ERROR") {
+            return Err(PresenceError::Rejected {
+                code: data["data"]["code"].as_i64().unwrap_or_default(),
+                message: data["data"]["message"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+            });
+        }
+        return Ok(data["data"].clone());
+    }
+    Err(PresenceError::Io(format!(
+        "Discord no contestó a {} ({})",
+        cmd, nonce
+    )))
+}
+
+// Envía la actividad ya construida (lo mismo que hace set_activity del
+// crate, pero con el JSON completo). Devuelve la actividad tal como la ha
+// aplicado Discord
+pub fn send_activity(
+    client: &mut DiscordIpcClient,
+    activity: Value,
+) -> StdResult<Value, PresenceError> {
+    command(
+        client,
+        "SET_ACTIVITY",
+        json!({
+            "pid": std::process::id(),
+            "activity": activity,
+        }),
+    )
+}
+
+// Borra la presencia (SET_ACTIVITY sin actividad)
+pub fn clear_activity(client: &mut DiscordIpcClient) -> StdResult<(), PresenceError> {
+    send_activity(client, Value::Null).map(|_| ())
+}
+
+// Textos que Discord ha aplicado distintos de los enviados (los recorta o
+// los limpia a su manera), para el log
+pub fn changed_texts(sent: &Value, applied: &Value) -> Vec<String> {
+    ["details", "state"]
+        .into_iter()
+        .filter_map(|field| {
+            let (sent, applied) = (sent[field].as_str()?, applied[field].as_str()?);
+            (sent != applied).then(|| format!("{}: '{}' → '{}'", field, sent, applied))
+        })
+        .collect()
+}
@@ -0,0 +1,86 @@
+// Perfiles con nombre: cada uno con su Application ID y su lista de
+// actividades (comisiones, streaming, trabajo...). La sección "discord" es el
+// perfil principal, llamado "default"; "profiles" añade el resto.
+//
+// El perfil activo se guarda en state.json en el directorio de estado para
+// mantenerlo entre reinicios. Al arrancar se usa, por orden: el guardado (si
+// sigue existiendo), default_profile y por último el principal.
+
+use super::*;
+
+// Nombre del perfil definido en la sección "discord"
+pub const MAIN_PROFILE: &str = "default";
+
+const STATE_FILE: &str = "state.json";
+
+// Estado que se conserva entre ejecuciones
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct SavedState {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    active_profile: Option<String>,
+}
+
+pub fn profile<'a>(config: &'a Config, name: &str) -> Option<&'a DiscordConfig> {
+    if name == MAIN_PROFILE {
+        Some(&config.discord)
+    } else {
+        config.profiles.get(name)
+    }
+}
+
+// El principal primero y el resto por nombre
+pub fn profile_names(config: &Config) -> Vec<String> {
+    std::iter::once(MAIN_PROFILE.to_string())
+        .chain(config.profiles.keys().cloned())
+        .collect()
+}
+
+// Perfil a usar cuando no hay uno activo (o el activo desapareció)
+pub fn default_profile(config: &Config) -> String {
+    match &config.default_profile {
+        Some(name) if profile(config, name).is_some() => name.clone(),
+        _ => MAIN_PROFILE.to_string(),
+    }
+}
+
+pub fn startup_profile(config: &Config) -> String {
+    match load_saved_state().active_profile {
+        Some(name) if profile(config, &name).is_some() => name,
+        Some(name) => {
+            log_error(&format!(
+                "⚠️  El perfil guardado '{}' ya no existe en la configuración",
+                name
+            ));
+            default_profile(config)
+        }
+        None => default_profile(config),
+    }
+}
+
+fn state_file_path() -> StdResult<PathBuf, String> {
+    Ok(paths::state_dir().join(STATE_FILE))
+}
+
+fn load_saved_state() -> SavedState {
+    state_file_path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_active_profile(name: &str) -> StdResult<(), String> {
+    let path = state_file_path()?;
+    let state = SavedState {
+        active_profile: Some(name.to_string()),
+    };
+    let content = serde_json::to_string_pretty(&state)
+        .map_err(|e| format!("Error serializando estado: {}", e))?;
+
+    // Escribir a un temporal y renombrar: un corte a medias no puede dejar
+    // un state.json truncado y perder el perfil activo
+    let temp_path = path.with_file_name(format!("{}.tmp", STATE_FILE));
+    fs::write(&temp_path, content)
+        .map_err(|e| format!("Error escribiendo {}: {}", temp_path.display(), e))?;
+    fs::rename(&temp_path, &path).map_err(|e| format!("Error guardando {}: {}", path.display(), e))
+}
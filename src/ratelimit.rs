@@ -0,0 +1,288 @@
+// Límite de actualizaciones de la presencia. Discord acepta unos 5
+// SET_ACTIVITY cada 20 segundos y descarta sin avisar los que pasan de ahí,
+// así que todo envío pasa por aquí antes de llegar al cliente IPC:
+//   - si la actividad es idéntica a la última enviada no se envía
+//   - si no quedan envíos en la ventana, la actividad queda pendiente y se
+//     envía en cuanto haya hueco; una más nueva sustituye a la pendiente
+//
+// La validación avisa de las actividades que duran menos que el intervalo
+// medio (MIN_INTERVAL_SECONDS): se verán algo más de lo configurado.
+
+use super::*;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+pub const MAX_UPDATES: usize = 5;
+pub const WINDOW: Duration = Duration::from_secs(20);
+// Intervalo medio que permite el límite
+pub const MIN_INTERVAL_SECONDS: u64 = WINDOW.as_secs() / MAX_UPDATES as u64;
+
+// Qué hacer con una actividad nueva
+#[derive(Debug)]
+pub enum Submit {
+    // Enviarla ya
+    Send(Value),
+    // Es la que ya se ve en Discord
+    Unchanged,
+    // Queda pendiente; hay hueco dentro de ese tiempo
+    Deferred(Duration),
+}
+
+#[derive(Debug, Default)]
+pub struct UpdateScheduler {
+    // Momentos de los últimos envíos dentro de la ventana
+    sent: VecDeque<Instant>,
+    // Lo último que se envió (null tras borrar la presencia)
+    last: Option<Value>,
+    // Nombre de la actividad (para saber cuál es si Discord la rechaza) y
+    // lo que hay que enviar
+    pending: Option<(String, Value)>,
+    // Para despertar la tarea que envía las pendientes
+    wakeup: Arc<tokio::sync::Notify>,
+}
+
+impl UpdateScheduler {
+    pub fn wakeup(&self) -> Arc<tokio::sync::Notify> {
+        self.wakeup.clone()
+    }
+
+    // Tiempo hasta que quede un envío libre (cero si ya lo hay)
+    fn wait(&mut self, now: Instant) -> Duration {
+        while self
+            .sent
+            .front()
+            .is_some_and(|sent| now.duration_since(*sent) >= WINDOW)
+        {
+            self.sent.pop_front();
+        }
+        if self.sent.len() < MAX_UPDATES {
+            return Duration::ZERO;
+        }
+        self.sent
+            .front()
+            .map(|oldest| (*oldest + WINDOW).saturating_duration_since(now))
+            .unwrap_or_default()
+    }
+
+    pub fn submit(&mut self, name: &str, activity: Value) -> Submit {
+        self.submit_at(name, activity, Instant::now())
+    }
+
+    fn submit_at(&mut self, name: &str, activity: Value, now: Instant) -> Submit {
+        if self.last.as_ref() == Some(&activity) {
+            // Lo pendiente ya no hace falta: lo que se ve es lo que se quiere
+            self.pending = None;
+            return Submit::Unchanged;
+        }
+        let wait = self.wait(now);
+        if wait.is_zero() {
+            self.pending = None;
+            return Submit::Send(activity);
+        }
+        self.pending = Some((name.to_string(), activity));
+        self.wakeup.notify_one();
+        Submit::Deferred(wait)
+    }
+
+    // La pendiente, si ya hay hueco para enviarla
+    pub fn take_due(&mut self) -> Option<(String, Value)> {
+        self.take_due_at(Instant::now())
+    }
+
+    fn take_due_at(&mut self, now: Instant) -> Option<(String, Value)> {
+        if self.pending.is_some() && self.wait(now).is_zero() {
+            self.pending.take()
+        } else {
+            None
+        }
+    }
+
+    // Tiempo hasta poder enviar la pendiente, si hay una
+    pub fn pending_wait(&mut self) -> Option<Duration> {
+        self.pending.as_ref()?;
+        Some(self.wait(Instant::now()))
+    }
+
+    // Apunta un envío hecho (activity es None al borrar la presencia)
+    pub fn record(&mut self, activity: Option<Value>) {
+        self.record_at(activity, Instant::now());
+    }
+
+    fn record_at(&mut self, activity: Option<Value>, now: Instant) {
+        self.sent.push_back(now);
+        self.last = Some(activity.unwrap_or(Value::Null));
+    }
+
+    // Apunta un envío que Discord rechazó: cuenta para el límite igual que
+    // uno aceptado, pero lo que se ve sigue siendo lo anterior
+    pub fn record_rejected(&mut self) {
+        self.record_rejected_at(Instant::now());
+    }
+
+    fn record_rejected_at(&mut self, now: Instant) {
+        self.sent.push_back(now);
+    }
+
+    // Una conexión nueva empieza sin actividad ni nada pendiente; los envíos
+    // recientes siguen contando
+    pub fn reset(&mut self) {
+        self.last = None;
+        self.pending = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // Envía (y apunta) una actividad que tiene que salir ya
+    fn send_value(scheduler: &mut UpdateScheduler, activity: &Value, now: Instant) {
+        match scheduler.submit_at("a", activity.clone(), now) {
+            Submit::Send(_) => scheduler.record_at(Some(activity.clone()), now),
+            other => panic!("{} no salió: {:?}", activity, other),
+        }
+    }
+
+    // Una distinta en cada llamada
+    fn send(scheduler: &mut UpdateScheduler, index: usize, now: Instant) {
+        send_value(
+            scheduler,
+            &json!({ "details": format!("actividad {}", index) }),
+            now,
+        );
+    }
+
+    #[test]
+    fn allows_max_updates_per_window() {
+        let start = Instant::now();
+        let mut scheduler = UpdateScheduler::default();
+        for index in 0..MAX_UPDATES {
+            send(
+                &mut scheduler,
+                index,
+                start + Duration::from_secs(index as u64),
+            );
+        }
+
+        let now = start + Duration::from_secs(5);
+        match scheduler.submit_at("b", json!({ "details": "otra" }), now) {
+            // Hay hueco cuando el primero sale de la ventana
+            Submit::Deferred(wait) => assert_eq!(wait, WINDOW - Duration::from_secs(5)),
+            other => panic!("debería quedar pendiente: {:?}", other),
+        }
+        assert_eq!(scheduler.take_due_at(now), None);
+        assert_eq!(
+            scheduler.take_due_at(start + WINDOW - Duration::from_millis(1)),
+            None
+        );
+        let (name, activity) = scheduler.take_due_at(start + WINDOW).expect("ya hay hueco");
+        assert_eq!(name, "b");
+        assert_eq!(activity, json!({ "details": "otra" }));
+    }
+
+    #[test]
+    fn window_slides() {
+        let start = Instant::now();
+        let mut scheduler = UpdateScheduler::default();
+        for index in 0..MAX_UPDATES {
+            send(&mut scheduler, index, start);
+        }
+        // Pasada la ventana vuelven a caber MAX_UPDATES
+        for index in 0..MAX_UPDATES {
+            send(&mut scheduler, MAX_UPDATES + index, start + WINDOW);
+        }
+        assert!(matches!(
+            scheduler.submit_at("a", json!({}), start + WINDOW),
+            Submit::Deferred(_)
+        ));
+    }
+
+    #[test]
+    fn newer_pending_replaces_older() {
+        let start = Instant::now();
+        let mut scheduler = UpdateScheduler::default();
+        for index in 0..MAX_UPDATES {
+            send(&mut scheduler, index, start);
+        }
+        scheduler.submit_at("b", json!({ "details": "b" }), start);
+        scheduler.submit_at("c", json!({ "details": "c" }), start);
+        let (name, _) = scheduler.take_due_at(start + WINDOW).expect("pendiente");
+        assert_eq!(name, "c");
+        assert_eq!(scheduler.take_due_at(start + WINDOW), None);
+    }
+
+    #[test]
+    fn skips_unchanged_payload() {
+        let now = Instant::now();
+        let mut scheduler = UpdateScheduler::default();
+        let activity = json!({ "details": "igual" });
+        send_value(&mut scheduler, &activity, now);
+        assert!(matches!(
+            scheduler.submit_at("a", activity.clone(), now),
+            Submit::Unchanged
+        ));
+        // Tras una conexión nueva se vuelve a enviar aunque sea la misma
+        scheduler.reset();
+        assert!(matches!(
+            scheduler.submit_at("a", activity, now),
+            Submit::Send(_)
+        ));
+    }
+
+    #[test]
+    fn unchanged_payload_drops_pending() {
+        let start = Instant::now();
+        let mut scheduler = UpdateScheduler::default();
+        for index in 0..MAX_UPDATES {
+            send(&mut scheduler, index, start);
+        }
+        scheduler.submit_at("b", json!({ "details": "b" }), start);
+        // Se vuelve a pedir lo que ya se ve: lo pendiente sobra
+        let shown = json!({ "details": format!("actividad {}", MAX_UPDATES - 1) });
+        assert!(matches!(
+            scheduler.submit_at("a", shown, start),
+            Submit::Unchanged
+        ));
+        assert_eq!(scheduler.pending_wait(), None);
+    }
+
+    #[test]
+    fn rejected_sends_count_towards_limit() {
+        let start = Instant::now();
+        let mut scheduler = UpdateScheduler::default();
+        let shown = json!({ "details": "aceptada" });
+        send_value(&mut scheduler, &shown, start);
+        for index in 1..MAX_UPDATES {
+            let activity = json!({ "details": format!("rechazada {}", index) });
+            assert!(matches!(
+                scheduler.submit_at("a", activity, start),
+                Submit::Send(_)
+            ));
+            scheduler.record_rejected_at(start);
+        }
+        assert!(matches!(
+            scheduler.submit_at("b", json!({ "details": "otra" }), start),
+            Submit::Deferred(_)
+        ));
+        // Lo que se ve sigue siendo la última aceptada
+        assert!(matches!(
+            scheduler.submit_at("a", shown, start),
+            Submit::Unchanged
+        ));
+    }
+
+    #[test]
+    fn clear_is_a_change() {
+        let now = Instant::now();
+        let mut scheduler = UpdateScheduler::default();
+        let activity = json!({ "details": "a" });
+        send_value(&mut scheduler, &activity, now);
+        scheduler.record_at(None, now);
+        assert!(matches!(
+            scheduler.submit_at("a", activity, now),
+            Submit::Send(_)
+        ));
+    }
+}
@@ -0,0 +1,414 @@
+// Orden en el que rotan las actividades de un perfil ("rotation" en la
+// sección discord o en cada perfil):
+//   "sequential"   en el orden del archivo (por defecto)
+//   "reverse"      en orden inverso
+//   "ping_pong"    de la primera a la última y vuelta atrás
+//   "shuffle"      orden aleatorio sin repetir hasta haber mostrado todas
+//   "weighted"     al azar según el "weight" de cada actividad (1 si no se
+//                  indica, 0 para que solo salga con select)
+//   {"playlist": ["a", "b", "a", "c"]}   secuencia fija por nombre; una
+//                  actividad puede aparecer varias veces o ninguna
+//
+// Las actividades fuera de su horario (ver schedule.rs) se saltan: available
+// indica cuáles se pueden mostrar ahora.
+
+use super::*;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+const DEFAULT_WEIGHT: u32 = 1;
+
+pub fn weight(activity: &ActivityConfig) -> u32 {
+    activity.weight.unwrap_or(DEFAULT_WEIGHT)
+}
+
+// Estado de la rotación entre una actividad y la siguiente
+#[derive(Debug)]
+pub struct Rotation {
+    // xorshift64; no hace falta más para elegir actividades
+    seed: u64,
+    // ping_pong: hacia la última actividad o de vuelta a la primera
+    forward: bool,
+    // shuffle: actividades que faltan por salir en esta vuelta
+    pending: Vec<usize>,
+    // playlist: posición de la última entrada mostrada
+    position: usize,
+}
+
+impl Default for Rotation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Rotation {
+    pub fn new() -> Self {
+        // RandomState ya trae una semilla aleatoria del sistema
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u32(std::process::id());
+        Self {
+            seed: hasher.finish() | 1,
+            forward: true,
+            pending: Vec::new(),
+            position: 0,
+        }
+    }
+
+    fn random(&mut self, below: u64) -> u64 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        self.seed % below
+    }
+
+    // Olvida la vuelta en curso (al recargar o cambiar de perfil)
+    pub fn reset(&mut self) {
+        self.forward = true;
+        self.pending.clear();
+        self.position = 0;
+    }
+
+    // Primera actividad a mostrar tras un reinicio de la rotación
+    pub fn first(
+        &mut self,
+        mode: &RotationMode,
+        activities: &[ActivityConfig],
+        available: &[bool],
+    ) -> usize {
+        self.reset();
+        let first = match mode {
+            RotationMode::Sequential | RotationMode::PingPong => 0,
+            RotationMode::Reverse => activities.len().saturating_sub(1),
+            RotationMode::Shuffle => self.next_shuffled(None, activities.len(), available),
+            RotationMode::Weighted => self.next_weighted(None, activities, available),
+            RotationMode::Playlist(names) => playlist_indices(names, activities)
+                .first()
+                .copied()
+                .unwrap_or(0),
+        };
+        if is_available(available, first) {
+            first
+        } else {
+            self.next(mode, first, activities, available)
+        }
+    }
+
+    // Siguiente actividad disponible; si no hay ninguna se queda la actual
+    pub fn next(
+        &mut self,
+        mode: &RotationMode,
+        current: usize,
+        activities: &[ActivityConfig],
+        available: &[bool],
+    ) -> usize {
+        let len = activities.len();
+        if len <= 1 {
+            return 0;
+        }
+        match mode {
+            RotationMode::Shuffle => self.next_shuffled(Some(current), len, available),
+            RotationMode::Weighted => self.next_weighted(Some(current), activities, available),
+            _ => {
+                // Con ida y vuelta o una lista hacen falta varias pasadas
+                // para haber probado todas
+                let playlist_len = match mode {
+                    RotationMode::Playlist(names) => names.len(),
+                    _ => 0,
+                };
+                // Al rebotar en una que no está disponible, ping_pong
+                // volvería a la actual: se sigue hasta otra
+                let bounced = |index: usize| index == current && *mode == RotationMode::PingPong;
+                let mut index = current;
+                for _ in 0..2 * (len + playlist_len) {
+                    index = self.step(mode, index, activities);
+                    if is_available(available, index) && !bounced(index) {
+                        return index;
+                    }
+                }
+                current
+            }
+        }
+    }
+
+    // Un paso de los órdenes que no son aleatorios
+    fn step(
+        &mut self,
+        mode: &RotationMode,
+        current: usize,
+        activities: &[ActivityConfig],
+    ) -> usize {
+        let len = activities.len();
+        match mode {
+            RotationMode::Sequential => (current + 1) % len,
+            RotationMode::Reverse => (current + len - 1) % len,
+            RotationMode::PingPong => {
+                if current == 0 {
+                    self.forward = true;
+                } else if current >= len - 1 {
+                    self.forward = false;
+                }
+                if self.forward {
+                    current + 1
+                } else {
+                    current - 1
+                }
+            }
+            RotationMode::Shuffle | RotationMode::Weighted => (current + 1) % len,
+            RotationMode::Playlist(names) => {
+                let indices = playlist_indices(names, activities);
+                if indices.is_empty() {
+                    return (current + 1) % len;
+                }
+                // Si la actual no es la de la posición guardada (select,
+                // recarga...) se sigue desde donde aparece en la lista
+                if indices.get(self.position) != Some(&current) {
+                    match indices.iter().position(|&index| index == current) {
+                        Some(position) => self.position = position,
+                        None => {
+                            self.position = 0;
+                            return indices[0];
+                        }
+                    }
+                }
+                self.position = (self.position + 1) % indices.len();
+                indices[self.position]
+            }
+        }
+    }
+
+    fn next_shuffled(&mut self, current: Option<usize>, len: usize, available: &[bool]) -> usize {
+        if len == 0 {
+            return 0;
+        }
+        self.pending.retain(|&index| index < len);
+        // Las que no están disponibles se pierden esta vuelta; si no queda
+        // ninguna se empieza otra
+        for _ in 0..2 {
+            while let Some(index) = self.pending.pop() {
+                if is_available(available, index) {
+                    return index;
+                }
+            }
+            self.refill(current, len);
+        }
+        current.unwrap_or(0)
+    }
+
+    fn refill(&mut self, current: Option<usize>, len: usize) {
+        self.pending = (0..len).collect();
+        // Fisher-Yates
+        for i in (1..len).rev() {
+            let j = self.random(i as u64 + 1) as usize;
+            self.pending.swap(i, j);
+        }
+        // Que la nueva vuelta no empiece repitiendo la actual
+        if len > 1 && self.pending.last() == current.as_ref() {
+            self.pending.swap(0, len - 1);
+        }
+    }
+
+    fn next_weighted(
+        &mut self,
+        current: Option<usize>,
+        activities: &[ActivityConfig],
+        available: &[bool],
+    ) -> usize {
+        let candidates: Vec<(usize, u64)> = activities
+            .iter()
+            .enumerate()
+            .map(|(index, activity)| (index, weight(activity) as u64))
+            .filter(|&(index, weight)| {
+                weight > 0 && Some(index) != current && is_available(available, index)
+            })
+            .collect();
+        let total: u64 = candidates.iter().map(|(_, weight)| weight).sum();
+        if total == 0 {
+            return current.unwrap_or(0);
+        }
+        let mut pick = self.random(total);
+        for (index, weight) in candidates {
+            if pick < weight {
+                return index;
+            }
+            pick -= weight;
+        }
+        current.unwrap_or(0)
+    }
+}
+
+fn is_available(available: &[bool], index: usize) -> bool {
+    available.get(index).copied().unwrap_or(true)
+}
+
+// Índices de las actividades de la lista; los nombres que no existen se
+// saltan (la validación ya avisa)
+fn playlist_indices(names: &[String], activities: &[ActivityConfig]) -> Vec<usize> {
+    names
+        .iter()
+        .filter_map(|name| {
+            activities
+                .iter()
+                .position(|activity| &activity.name == name)
+        })
+        .collect()
+}
+
+pub fn describe(mode: &RotationMode) -> String {
+    match mode {
+        RotationMode::Sequential => "secuencial".to_string(),
+        RotationMode::Reverse => "inversa".to_string(),
+        RotationMode::PingPong => "ida y vuelta".to_string(),
+        RotationMode::Shuffle => "aleatoria sin repetir".to_string(),
+        RotationMode::Weighted => "aleatoria por peso".to_string(),
+        RotationMode::Playlist(names) => format!("lista fija ({} entradas)", names.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn activities(weights: &[Option<u32>]) -> Vec<ActivityConfig> {
+        weights
+            .iter()
+            .enumerate()
+            .map(|(index, weight)| ActivityConfig {
+                name: format!("a{}", index),
+                weight: *weight,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    // Las siguientes count actividades desde la primera
+    fn sequence(
+        mode: &RotationMode,
+        activities: &[ActivityConfig],
+        available: &[bool],
+        count: usize,
+    ) -> Vec<usize> {
+        let mut rotation = Rotation::new();
+        let mut current = rotation.first(mode, activities, available);
+        let mut shown = vec![current];
+        for _ in 1..count {
+            current = rotation.next(mode, current, activities, available);
+            shown.push(current);
+        }
+        shown
+    }
+
+    #[test]
+    fn sequential_and_reverse() {
+        let list = activities(&[None; 3]);
+        assert_eq!(
+            sequence(&RotationMode::Sequential, &list, &[], 5),
+            [0, 1, 2, 0, 1]
+        );
+        assert_eq!(
+            sequence(&RotationMode::Reverse, &list, &[], 5),
+            [2, 1, 0, 2, 1]
+        );
+    }
+
+    #[test]
+    fn skips_unavailable() {
+        let list = activities(&[None; 3]);
+        let available = [true, false, true];
+        assert_eq!(
+            sequence(&RotationMode::Sequential, &list, &available, 4),
+            [0, 2, 0, 2]
+        );
+        // Si la primera no está disponible se empieza por la siguiente
+        assert_eq!(
+            sequence(&RotationMode::Reverse, &list, &[true, true, false], 3),
+            [1, 0, 1]
+        );
+        // Sin ninguna disponible se queda la actual
+        assert_eq!(
+            Rotation::new().next(&RotationMode::Sequential, 1, &list, &[false; 3]),
+            1
+        );
+    }
+
+    #[test]
+    fn ping_pong_turns_around() {
+        let mode = RotationMode::PingPong;
+        assert_eq!(sequence(&mode, &activities(&[None]), &[], 3), [0, 0, 0]);
+        assert_eq!(
+            sequence(&mode, &activities(&[None; 2]), &[], 5),
+            [0, 1, 0, 1, 0]
+        );
+        assert_eq!(
+            sequence(&mode, &activities(&[None; 3]), &[], 7),
+            [0, 1, 2, 1, 0, 1, 2]
+        );
+    }
+
+    #[test]
+    fn ping_pong_skips_unavailable_at_the_end() {
+        let list = activities(&[None; 3]);
+        assert_eq!(
+            sequence(&RotationMode::PingPong, &list, &[true, true, false], 5),
+            [0, 1, 0, 1, 0]
+        );
+    }
+
+    #[test]
+    fn shuffle_shows_all_before_repeating() {
+        let list = activities(&[None; 5]);
+        let shown = sequence(&RotationMode::Shuffle, &list, &[], 20);
+        for round in shown.chunks(5) {
+            let mut round = round.to_vec();
+            round.sort_unstable();
+            assert_eq!(round, [0, 1, 2, 3, 4]);
+        }
+        // Tampoco se repite al empezar una vuelta nueva
+        assert!(shown.windows(2).all(|pair| pair[0] != pair[1]));
+    }
+
+    #[test]
+    fn weighted_never_picks_zero_weight() {
+        let list = activities(&[Some(1), Some(0), Some(3)]);
+        let shown = sequence(&RotationMode::Weighted, &list, &[], 50);
+        assert!(!shown.contains(&1));
+        // Nunca repite la actual, así que alterna entre las dos que quedan
+        assert!(shown.windows(2).all(|pair| pair[0] != pair[1]));
+    }
+
+    #[test]
+    fn weighted_with_all_zero_weights_stays() {
+        let list = activities(&[Some(0), Some(0)]);
+        let mut rotation = Rotation::new();
+        assert_eq!(rotation.next(&RotationMode::Weighted, 1, &list, &[]), 1);
+        assert_eq!(rotation.first(&RotationMode::Weighted, &list, &[]), 0);
+    }
+
+    #[test]
+    fn playlist_repeats_entries_and_skips_missing_names() {
+        let list = activities(&[None; 3]);
+        let names = |names: &[&str]| {
+            RotationMode::Playlist(names.iter().map(|name| name.to_string()).collect())
+        };
+
+        assert_eq!(
+            sequence(&names(&["a2", "a0", "a2", "a1"]), &list, &[], 6),
+            [2, 0, 2, 1, 2, 0]
+        );
+        assert_eq!(
+            sequence(&names(&["a0", "missing", "a1"]), &list, &[], 4),
+            [0, 1, 0, 1]
+        );
+        // Sin ningún nombre válido, en orden
+        assert_eq!(sequence(&names(&["missing"]), &list, &[], 4), [0, 1, 2, 0]);
+    }
+
+    #[test]
+    fn playlist_continues_after_select() {
+        let list = activities(&[None; 3]);
+        let mode = RotationMode::Playlist(vec!["a0".into(), "a1".into(), "a2".into()]);
+        let mut rotation = Rotation::new();
+        rotation.first(&mode, &list, &[]);
+        // Con select se salta a a2: sigue desde donde aparece en la lista
+        assert_eq!(rotation.next(&mode, 2, &list, &[]), 0);
+    }
+}
@@ -0,0 +1,323 @@
+// Horarios de las actividades. Una actividad con "schedule" solo entra en la
+// rotación dentro de su horario; puede ser un horario propio o el nombre de
+// uno de "schedules" en la sección, para compartirlo entre varias:
+//   "schedules": { "office": { "days": ["mon-fri"], "hours": ["09:00-18:00"] } }
+//   { "name": "commissions", "schedule": "office", ... }
+//
+// Campos, todos opcionales (los que no se indican no restringen nada):
+//   days    días de la semana: "mon".."sun", rangos "mon-fri", "weekdays"
+//           y "weekends"
+//   hours   franjas "HH:MM-HH:MM"; si el final es anterior al inicio
+//           ("22:00-02:00") la franja sigue hasta el día siguiente y cuenta
+//           como del día en que empezó
+//   from / until   fechas "YYYY-MM-DD", ambas incluidas
+//
+// "quiet_hours" en la sección usa el mismo formato: mientras se cumple, la
+// presencia se borra de Discord. Todo se evalúa en la hora local del equipo.
+
+use super::*;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveTime, Weekday};
+use serde::de::{self, MapAccess, Visitor};
+use std::fmt;
+
+// Un texto es el nombre de un horario y un objeto un horario propio. Con
+// #[serde(untagged)] un campo mal escrito solo daría "no coincide con
+// ninguna variante"; así llega el error de ScheduleConfig
+impl<'de> Deserialize<'de> for ScheduleRef {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> StdResult<Self, D::Error> {
+        struct ScheduleRefVisitor;
+
+        impl<'de> Visitor<'de> for ScheduleRefVisitor {
+            type Value = ScheduleRef;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter
+                    .write_str("el nombre de un horario o un horario (days, hours, from, until)")
+            }
+
+            fn visit_str<E: de::Error>(self, name: &str) -> StdResult<ScheduleRef, E> {
+                Ok(ScheduleRef::Named(name.to_string()))
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, map: M) -> StdResult<ScheduleRef, M::Error> {
+                ScheduleConfig::deserialize(de::value::MapAccessDeserializer::new(map))
+                    .map(ScheduleRef::Inline)
+            }
+        }
+
+        deserializer.deserialize_any(ScheduleRefVisitor)
+    }
+}
+
+// chrono acepta "mon" y "monday", sin distinguir mayúsculas
+fn parse_weekday(text: &str) -> StdResult<Weekday, String> {
+    text.trim().parse::<Weekday>().map_err(|_| {
+        format!(
+            "'{}' no es un día de la semana (usa mon, tue, wed, thu, fri, sat o sun)",
+            text.trim()
+        )
+    })
+}
+
+// Días que incluye una entrada de "days"
+fn parse_days(entry: &str) -> StdResult<Vec<Weekday>, String> {
+    match entry.trim().to_lowercase().as_str() {
+        "weekdays" => return parse_days("mon-fri"),
+        "weekends" => return parse_days("sat-sun"),
+        _ => {}
+    }
+    let Some((first, last)) = entry.split_once('-') else {
+        return parse_weekday(entry).map(|day| vec![day]);
+    };
+    let (first, last) = (parse_weekday(first)?, parse_weekday(last)?);
+    let mut days = vec![first];
+    let mut day = first;
+    while day != last {
+        day = day.succ();
+        days.push(day);
+    }
+    Ok(days)
+}
+
+fn parse_time(text: &str) -> StdResult<NaiveTime, String> {
+    NaiveTime::parse_from_str(text.trim(), "%H:%M")
+        .map_err(|_| format!("'{}' no es una hora válida (usa HH:MM)", text.trim()))
+}
+
+// "09:00-18:00"
+fn parse_window(entry: &str) -> StdResult<(NaiveTime, NaiveTime), String> {
+    let Some((start, end)) = entry.split_once('-') else {
+        return Err(format!(
+            "'{}' no es una franja válida (usa \"09:00-18:00\")",
+            entry
+        ));
+    };
+    let (start, end) = (parse_time(start)?, parse_time(end)?);
+    if start == end {
+        return Err(format!(
+            "la franja '{}' empieza y termina a la misma hora",
+            entry
+        ));
+    }
+    Ok((start, end))
+}
+
+fn parse_date(text: &str) -> StdResult<NaiveDate, String> {
+    NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d")
+        .map_err(|_| format!("'{}' no es una fecha válida (usa YYYY-MM-DD)", text.trim()))
+}
+
+// Errores de un horario, para la validación
+pub fn check(schedule: &ScheduleConfig) -> Vec<String> {
+    let mut errors: Vec<String> = schedule
+        .days
+        .iter()
+        .filter_map(|entry| parse_days(entry).err())
+        .chain(
+            schedule
+                .hours
+                .iter()
+                .filter_map(|entry| parse_window(entry).err()),
+        )
+        .collect();
+
+    let from = schedule.from.as_deref().map(parse_date);
+    let until = schedule.until.as_deref().map(parse_date);
+    for date in [&from, &until].into_iter().flatten() {
+        if let Err(message) = date {
+            errors.push(message.clone());
+        }
+    }
+    if let (Some(Ok(from)), Some(Ok(until))) = (from, until) {
+        if from > until {
+            errors.push(format!("from ({}) es posterior a until ({})", from, until));
+        }
+    }
+    errors
+}
+
+fn day_allowed(schedule: &ScheduleConfig, day: Weekday) -> bool {
+    schedule.days.is_empty()
+        || schedule
+            .days
+            .iter()
+            .filter_map(|entry| parse_days(entry).ok())
+            .any(|days| days.contains(&day))
+}
+
+fn date_allowed(schedule: &ScheduleConfig, date: NaiveDate) -> bool {
+    let after_from = match schedule.from.as_deref().map(parse_date) {
+        Some(Ok(from)) => date >= from,
+        _ => true,
+    };
+    let before_until = match schedule.until.as_deref().map(parse_date) {
+        Some(Ok(until)) => date <= until,
+        _ => true,
+    };
+    after_from && before_until
+}
+
+// Los días y fechas se comprueban con el día en que empezó la franja
+fn day_matches(schedule: &ScheduleConfig, date: NaiveDate) -> bool {
+    day_allowed(schedule, date.weekday()) && date_allowed(schedule, date)
+}
+
+// Si el horario se cumple en este momento. Las entradas inválidas (que la
+// validación ya rechaza) se ignoran
+pub fn is_active(schedule: &ScheduleConfig, now: &DateTime<Local>) -> bool {
+    let today = now.date_naive();
+    let time = now.time();
+    if schedule.hours.is_empty() {
+        return day_matches(schedule, today);
+    }
+    schedule
+        .hours
+        .iter()
+        .filter_map(|entry| parse_window(entry).ok())
+        .any(|(start, end)| {
+            if start < end {
+                start <= time && time < end && day_matches(schedule, today)
+            } else {
+                // Franja que pasa de medianoche
+                (time >= start && day_matches(schedule, today))
+                    || (time < end && day_matches(schedule, today - Duration::days(1)))
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn schedule(
+        days: &[&str],
+        hours: &[&str],
+        from: Option<&str>,
+        until: Option<&str>,
+    ) -> ScheduleConfig {
+        ScheduleConfig {
+            days: days.iter().map(|day| day.to_string()).collect(),
+            hours: hours.iter().map(|window| window.to_string()).collect(),
+            from: from.map(str::to_string),
+            until: until.map(str::to_string),
+        }
+    }
+
+    // Enero de 2026: el día 9 es viernes
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(2026, 1, day, hour, minute, 0)
+            .single()
+            .expect("hora local sin ambigüedad")
+    }
+
+    #[test]
+    fn day_ranges() {
+        assert_eq!(
+            parse_days("mon-wed"),
+            Ok(vec![Weekday::Mon, Weekday::Tue, Weekday::Wed])
+        );
+        assert_eq!(parse_days("Sat"), Ok(vec![Weekday::Sat]));
+        assert_eq!(parse_days("weekends"), Ok(vec![Weekday::Sat, Weekday::Sun]));
+        assert_eq!(parse_days("weekdays").map(|days| days.len()), Ok(5));
+        assert!(parse_days("mon-funday").is_err());
+        assert!(parse_days("").is_err());
+    }
+
+    #[test]
+    fn day_range_wraps_around_the_week() {
+        assert_eq!(
+            parse_days("fri-mon"),
+            Ok(vec![Weekday::Fri, Weekday::Sat, Weekday::Sun, Weekday::Mon])
+        );
+        let weekend = schedule(&["fri-mon"], &[], None, None);
+        assert!(is_active(&weekend, &at(9, 12, 0)));
+        assert!(is_active(&weekend, &at(11, 12, 0)));
+        assert!(is_active(&weekend, &at(12, 12, 0)));
+        assert!(!is_active(&weekend, &at(13, 12, 0)));
+        // Un rango de un solo día
+        assert_eq!(parse_days("tue-tue"), Ok(vec![Weekday::Tue]));
+    }
+
+    #[test]
+    fn window_within_a_day() {
+        let office = schedule(&[], &["09:00-18:00"], None, None);
+        assert!(!is_active(&office, &at(9, 8, 59)));
+        assert!(is_active(&office, &at(9, 9, 0)));
+        assert!(is_active(&office, &at(9, 17, 59)));
+        // El final no está incluido
+        assert!(!is_active(&office, &at(9, 18, 0)));
+    }
+
+    #[test]
+    fn window_across_midnight() {
+        let night = schedule(&[], &["22:00-02:00"], None, None);
+        assert!(is_active(&night, &at(9, 23, 30)));
+        assert!(is_active(&night, &at(10, 1, 59)));
+        assert!(!is_active(&night, &at(10, 2, 0)));
+        assert!(!is_active(&night, &at(10, 12, 0)));
+        assert!(!is_active(&night, &at(9, 21, 59)));
+    }
+
+    #[test]
+    fn window_across_midnight_counts_for_the_starting_day() {
+        // Viernes por la noche: la madrugada del sábado también cuenta
+        let friday_night = schedule(&["fri"], &["22:00-02:00"], None, None);
+        assert!(is_active(&friday_night, &at(9, 23, 0)));
+        assert!(is_active(&friday_night, &at(10, 1, 0)));
+        // La madrugada del viernes es del jueves
+        assert!(!is_active(&friday_night, &at(9, 1, 0)));
+        assert!(!is_active(&friday_night, &at(10, 23, 0)));
+    }
+
+    #[test]
+    fn date_range_is_inclusive() {
+        let campaign = schedule(&[], &[], Some("2026-01-09"), Some("2026-01-11"));
+        assert!(!is_active(&campaign, &at(8, 23, 59)));
+        assert!(is_active(&campaign, &at(9, 0, 0)));
+        assert!(is_active(&campaign, &at(11, 23, 59)));
+        assert!(!is_active(&campaign, &at(12, 0, 0)));
+
+        let open_ended = schedule(&[], &[], Some("2026-01-10"), None);
+        assert!(!is_active(&open_ended, &at(9, 12, 0)));
+        assert!(is_active(&open_ended, &at(31, 12, 0)));
+    }
+
+    #[test]
+    fn date_range_with_window_across_midnight() {
+        // La madrugada después del último día todavía es de ese día
+        let last_night = schedule(&[], &["22:00-02:00"], None, Some("2026-01-09"));
+        assert!(is_active(&last_night, &at(10, 1, 0)));
+        assert!(!is_active(&last_night, &at(10, 23, 0)));
+    }
+
+    #[test]
+    fn empty_schedule_always_matches() {
+        assert!(is_active(&ScheduleConfig::default(), &at(9, 3, 0)));
+    }
+
+    #[test]
+    fn check_reports_invalid_entries() {
+        assert!(check(&schedule(
+            &["mon-fri"],
+            &["09:00-18:00"],
+            Some("2026-01-01"),
+            None
+        ))
+        .is_empty());
+        assert_eq!(check(&schedule(&["someday"], &[], None, None)).len(), 1);
+        assert_eq!(
+            check(&schedule(&[], &["9-18", "10:00-10:00"], None, None)).len(),
+            2
+        );
+        assert_eq!(
+            check(&schedule(&[], &[], Some("2026-02-01"), Some("2026-01-01"))).len(),
+            1
+        );
+        assert_eq!(
+            check(&schedule(&[], &[], Some("2026-13-01"), None)).len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,303 @@
+// Asistente de configuración inicial. El config.json por defecto trae un
+// Application ID de ejemplo con el que Discord nunca va a conectar; este
+// asistente pregunta el ID real y unas primeras actividades y escribe el
+// archivo de configuración en uso (config.json salvo que haya otro formato).
+
+use super::*;
+use std::io::BufRead;
+
+fn prompt(label: &str) -> StdResult<String, String> {
+    print!("{}", label);
+    io::stdout().flush().map_err(|e| e.to_string())?;
+
+    let mut line = String::new();
+    let read = io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .map_err(|e| format!("Error leyendo de la terminal: {}", e))?;
+    if read == 0 {
+        return Err("Entrada cerrada - asistente cancelado".to_string());
+    }
+    Ok(line.trim().to_string())
+}
+
+// Repite la pregunta hasta que check acepte la respuesta
+fn prompt_valid(
+    label: &str,
+    check: impl Fn(&str) -> StdResult<(), String>,
+) -> StdResult<String, String> {
+    loop {
+        let answer = prompt(label)?;
+        match check(&answer) {
+            Ok(()) => return Ok(answer),
+            Err(e) => println!("   ❌ {}", e),
+        }
+    }
+}
+
+fn prompt_yes_no(label: &str, default: bool) -> StdResult<bool, String> {
+    let hint = if default { "[S/n]" } else { "[s/N]" };
+    loop {
+        let answer = prompt(&format!("{} {} ", label, hint))?.to_lowercase();
+        match answer.as_str() {
+            "" => return Ok(default),
+            "s" | "si" | "sí" | "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => println!("   Responde 's' o 'n'"),
+        }
+    }
+}
+
+fn check_text(value: &str) -> StdResult<(), String> {
+    if (2..=128).contains(&value.len()) {
+        Ok(())
+    } else {
+        Err("Debe tener entre 2 y 128 bytes".to_string())
+    }
+}
+
+fn optional(value: String) -> Option<String> {
+    if value.is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+fn prompt_activity(existing: &[ActivityConfig]) -> StdResult<Option<ActivityConfig>, String> {
+    let name = prompt("Nombre interno de la actividad (vacío para terminar): ")?;
+    if name.is_empty() {
+        return Ok(None);
+    }
+    if existing.iter().any(|a| a.name == name) {
+        println!("   ❌ Ya hay una actividad llamada '{}'", name);
+        return prompt_activity(existing);
+    }
+
+    let details = prompt_valid("  Detalles (primera línea): ", check_text)?;
+    let state = prompt_valid("  Estado (segunda línea): ", check_text)?;
+    let large_image = optional(prompt(
+        "  Imagen grande (nombre del asset en Discord, opcional): ",
+    )?);
+    let large_text = match large_image {
+        Some(_) => optional(prompt_valid(
+            "  Texto al pasar por la imagen (opcional): ",
+            |value| {
+                if value.is_empty() {
+                    Ok(())
+                } else {
+                    check_text(value)
+                }
+            },
+        )?),
+        None => None,
+    };
+    let duration = prompt_valid("  Segundos en pantalla [30]: ", |value| {
+        if value.is_empty() || value.parse::<u64>().map(|n| n > 0).unwrap_or(false) {
+            Ok(())
+        } else {
+            Err("Debe ser un número mayor que 0".to_string())
+        }
+    })?;
+
+    let mut buttons = Vec::new();
+    while buttons.len() < 2 {
+        let label = prompt_valid("  Texto de un botón (opcional): ", |value| {
+            if value.chars().count() <= 32 {
+                Ok(())
+            } else {
+                Err("Máximo 32 caracteres".to_string())
+            }
+        })?;
+        if label.is_empty() {
+            break;
+        }
+        let url = prompt_valid("  URL del botón: ", |value| {
+            if validate::is_valid_button_url(value) {
+                Ok(())
+            } else {
+                Err("Debe ser una URL http:// o https://".to_string())
+            }
+        })?;
+        buttons.push(ButtonConfig { label, url });
+    }
+
+    Ok(Some(ActivityConfig {
+        name,
+        details,
+        state,
+        large_image,
+        large_text,
+        small_image: None,
+        small_text: None,
+        duration_seconds: duration.parse().unwrap_or(30),
+        buttons: if buttons.is_empty() {
+            None
+        } else {
+            Some(buttons)
+        },
+        ..Default::default()
+    }))
+}
+
+pub fn run_setup_wizard() -> StdResult<(), String> {
+    paths::ensure_config_dir()?;
+    let config_path = formats::active_config_path()?;
+    let format =
+        formats::ConfigFormat::from_path(&config_path).unwrap_or(formats::ConfigFormat::Json);
+
+    println!();
+    println!("🧙 Asistente de configuración de Lorian Workspace");
+    println!();
+    println!("1. Abre https://discord.com/developers/applications");
+    println!("2. Crea una aplicación (su nombre es lo que aparecerá como \"Jugando a ...\")");
+    println!("3. Copia el Application ID de la página General Information");
+    println!();
+
+    let app_id = prompt_valid("Application ID: ", |value| {
+        if is_placeholder_app_id(value) {
+            Err("Ese es el ID de ejemplo, usa el de tu aplicación".to_string())
+        } else if validate::is_valid_snowflake(value) {
+            Ok(())
+        } else {
+            Err("Debe ser un número de 17 a 20 dígitos".to_string())
+        }
+    })?;
+
+    println!();
+    println!("Ahora las actividades que irán rotando en tu perfil.");
+    let mut activities = Vec::new();
+    while let Some(activity) = prompt_activity(&activities)? {
+        activities.push(activity);
+        println!("   ✅ Actividad añadida ({} en total)", activities.len());
+    }
+
+    // Solo el archivo principal, sin combinar los includes: se reescribe
+    // tal cual y los archivos incluidos no se tocan
+    let existing = fs::read_to_string(&config_path)
+        .ok()
+        .and_then(|content| validate::check_config_source(&content, format, false).0);
+
+    // Sin actividades nuevas se conservan las actuales (o las de ejemplo)
+    if activities.is_empty() {
+        println!("ℹ️  No se añadieron actividades - se conservan las existentes");
+        activities = existing
+            .as_ref()
+            .map(|config| config.discord.activities.clone())
+            .unwrap_or_else(|| get_default_config().discord.activities);
+    }
+
+    // El asistente solo rellena el perfil principal; el resto se conserva
+    let existing = existing.unwrap_or_else(get_default_config);
+    let config = Config {
+        version: migrate::CONFIG_VERSION,
+        include: existing.include,
+        default_profile: existing.default_profile,
+        discord: DiscordConfig {
+            app_id,
+            timestamps: existing.discord.timestamps,
+            rotation: existing.discord.rotation,
+            schedules: existing.discord.schedules,
+            quiet_hours: existing.discord.quiet_hours,
+            reconnect: existing.discord.reconnect,
+            activities,
+        },
+        profiles: existing.profiles,
+    };
+    let config_source = format
+        .serialize(&config)
+        .map_err(|e| format!("Error serializando configuración: {}", e))?;
+
+    // Repasar con las mismas reglas que al cargar antes de escribir nada
+    let (_, diagnostics) = validate::check_config_source(&config_source, format, true);
+    if diagnostics.iter().any(|d| d.is_error()) {
+        for diagnostic in &diagnostics {
+            println!("❌ {}", diagnostic);
+        }
+        return Err("La configuración generada no es válida".to_string());
+    }
+
+    if config_path.exists()
+        && !prompt_yes_no(&format!("¿Sobrescribir {}?", config_path.display()), true)?
+    {
+        return Err(format!(
+            "Asistente cancelado - {} no se modificó",
+            config_path.display()
+        ));
+    }
+
+    fs::write(&config_path, config_source)
+        .map_err(|e| format!("Error escribiendo {}: {}", config_path.display(), e))?;
+    println!("✅ Configuración guardada en {}", config_path.display());
+    log_info(&format!(
+        "🧙 Configuración creada con el asistente: {}",
+        config_path.display()
+    ));
+    Ok(())
+}
+
+// Subcomando `setup`. Devuelve el código de salida del proceso.
+pub fn run_setup_command() -> i32 {
+    cli::attach_console();
+    match run_setup_wizard() {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            1
+        }
+    }
+}
+
+// Al arrancar con el ID de ejemplo: ofrecer el asistente si hay alguien
+// para contestarlo. Devuelve true si se escribió una configuración nueva.
+#[cfg(not(windows))]
+pub fn offer_setup_wizard() -> bool {
+    use std::io::IsTerminal;
+
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        log_info("💡 Ejecuta `lorianworkspace setup` para configurar tu Application ID");
+        return false;
+    }
+
+    match prompt_yes_no(
+        "⚠️  config.json usa el Application ID de ejemplo. ¿Configurarlo ahora?",
+        true,
+    ) {
+        Ok(true) => match run_setup_wizard() {
+            Ok(()) => true,
+            Err(e) => {
+                log_error(&format!("❌ {}", e));
+                false
+            }
+        },
+        _ => false,
+    }
+}
+
+// En Windows la app no tiene consola: se pregunta con un diálogo y, si se
+// acepta, se abre una consola solo para el asistente
+#[cfg(windows)]
+pub fn offer_setup_wizard() -> bool {
+    let answer = unsafe {
+        MessageBoxW(
+            None,
+            w!("config.json usa el Application ID de ejemplo, así que Discord no mostrará nada.\n\n¿Quieres configurarlo ahora?"),
+            w!("Lorian Workspace - Configuración inicial"),
+            MB_YESNO | MB_ICONQUESTION,
+        )
+    };
+    if answer != IDYES {
+        log_info("💡 Ejecuta `lorianworkspace setup` para configurar tu Application ID");
+        return false;
+    }
+
+    show_console();
+    let result = run_setup_wizard();
+    if let Err(e) = &result {
+        log_error(&format!("❌ {}", e));
+        println!("❌ {}", e);
+        let _ = prompt("Pulsa Enter para cerrar esta ventana...");
+    }
+    hide_console();
+    result.is_ok()
+}
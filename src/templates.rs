@@ -0,0 +1,365 @@
+// Plantillas en los textos de las actividades (details, state, large_text,
+// small_text y etiquetas de botones). Se rellenan cada vez que se envía la
+// actividad a Discord:
+//   {time} / {time:%H:%M:%S}   hora actual (formato strftime, por defecto %H:%M)
+//   {date} / {date:%d/%m}      fecha actual (por defecto %Y-%m-%d)
+//   {uptime}                   tiempo que lleva abierta la app (2h 15m)
+//   {activity_index}           posición de la actividad (empezando en 1)
+//   {activity_count}           número de actividades en rotación
+//   {profile}                  perfil activo
+//   {env.NOMBRE}               variable de entorno NOMBRE (vacía si no existe)
+//
+// Solo es un marcador una llave seguida de una letra o "_"; "{{" y "}}" son
+// llaves literales. Tampoco cuenta "${", que es de las variables de la
+// configuración (ver expand.rs). Los marcadores desconocidos son errores de
+// validación.
+
+use super::*;
+use chrono::format::{Item, StrftimeItems};
+use std::time::{Duration, Instant};
+
+const DEFAULT_TIME_FORMAT: &str = "%H:%M";
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+const AVAILABLE: &str = "time, date, uptime, activity_index, activity_count, profile, env.NOMBRE";
+
+// Datos con los que se rellenan las plantillas
+pub struct TemplateContext<'a> {
+    pub started_at: Instant,
+    // Empezando en 0
+    pub activity_index: usize,
+    pub activity_count: usize,
+    pub profile: &'a str,
+}
+
+enum Segment<'a> {
+    Text(&'a str),
+    Placeholder { name: &'a str, arg: Option<&'a str> },
+}
+
+fn parse(template: &str) -> StdResult<Vec<Segment<'_>>, String> {
+    let mut segments = Vec::new();
+    let mut text_start = 0;
+    let mut index = 0;
+    let bytes = template.as_bytes();
+
+    while index < bytes.len() {
+        match bytes[index] {
+            // {{ y }}: se queda una sola llave
+            b'{' | b'}' if bytes.get(index + 1) == Some(&bytes[index]) => {
+                segments.push(Segment::Text(&template[text_start..index + 1]));
+                index += 2;
+                text_start = index;
+            }
+            b'{' if index > 0 && bytes[index - 1] == b'$' => index += 1,
+            b'{' if bytes
+                .get(index + 1)
+                .is_some_and(|next| next.is_ascii_alphabetic() || *next == b'_') =>
+            {
+                let Some(length) = template[index..].find('}') else {
+                    return Err(format!(
+                        "falta la '}}' de cierre en '{}'",
+                        &template[index..]
+                    ));
+                };
+                segments.push(Segment::Text(&template[text_start..index]));
+                let inner = &template[index + 1..index + length];
+                let (name, arg) = match inner.split_once(':') {
+                    Some((name, arg)) => (name, Some(arg)),
+                    None => (inner, None),
+                };
+                segments.push(Segment::Placeholder { name, arg });
+                index += length + 1;
+                text_start = index;
+            }
+            _ => index += 1,
+        }
+    }
+    segments.push(Segment::Text(&template[text_start..]));
+    Ok(segments)
+}
+
+fn is_valid_strftime(format: &str) -> bool {
+    !StrftimeItems::new(format).any(|item| matches!(item, Item::Error))
+}
+
+fn check_placeholder(name: &str, arg: Option<&str>) -> StdResult<(), String> {
+    match (name, arg) {
+        ("time" | "date", Some(format)) if !is_valid_strftime(format) => Err(format!(
+            "'{}' no es un formato de fecha/hora válido en {{{}:{}}}",
+            format, name, format
+        )),
+        ("time" | "date", _) => Ok(()),
+        ("uptime" | "activity_index" | "activity_count" | "profile", None) => Ok(()),
+        ("uptime" | "activity_index" | "activity_count" | "profile", Some(_)) => {
+            Err(format!("{{{}}} no admite argumentos", name))
+        }
+        _ => match name.strip_prefix("env.") {
+            Some(variable) if !expand::is_valid_name(variable) => Err(format!(
+                "'{}' no es un nombre de variable de entorno válido en {{{}}}",
+                variable, name
+            )),
+            Some(_) if arg.is_some() => Err(format!("{{{}}} no admite argumentos", name)),
+            Some(_) => Ok(()),
+            None => Err(format!(
+                "marcador desconocido {{{}}} (disponibles: {}; usa {{{{ y }}}} para llaves literales)",
+                name, AVAILABLE
+            )),
+        },
+    }
+}
+
+// Problemas de una plantilla, para la validación
+pub fn check(template: &str) -> Vec<String> {
+    match parse(template) {
+        Ok(segments) => segments
+            .iter()
+            .filter_map(|segment| match segment {
+                Segment::Placeholder { name, arg } => check_placeholder(name, *arg).err(),
+                Segment::Text(_) => None,
+            })
+            .collect(),
+        Err(message) => vec![message],
+    }
+}
+
+pub fn has_placeholders(template: &str) -> bool {
+    parse(template).is_ok_and(|segments| {
+        segments
+            .iter()
+            .any(|segment| matches!(segment, Segment::Placeholder { .. }))
+    })
+}
+
+fn format_uptime(elapsed: Duration) -> String {
+    let minutes = elapsed.as_secs() / 60;
+    let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+fn render_placeholder(name: &str, arg: Option<&str>, context: &TemplateContext) -> String {
+    match name {
+        "time" => chrono::Local::now()
+            .format(arg.unwrap_or(DEFAULT_TIME_FORMAT))
+            .to_string(),
+        "date" => chrono::Local::now()
+            .format(arg.unwrap_or(DEFAULT_DATE_FORMAT))
+            .to_string(),
+        "uptime" => format_uptime(context.started_at.elapsed()),
+        "activity_index" => (context.activity_index + 1).to_string(),
+        "activity_count" => context.activity_count.to_string(),
+        "profile" => context.profile.to_string(),
+        _ => name
+            .strip_prefix("env.")
+            .and_then(|variable| env::var(variable).ok())
+            .unwrap_or_default(),
+    }
+}
+
+// Rellena la plantilla. Una plantilla inválida (la validación ya lo impide)
+// se deja tal cual
+pub fn render(template: &str, context: &TemplateContext) -> String {
+    let Ok(segments) = parse(template) else {
+        return template.to_string();
+    };
+    let mut rendered = String::with_capacity(template.len());
+    for segment in segments {
+        match segment {
+            Segment::Text(text) => rendered.push_str(text),
+            Segment::Placeholder { name, arg } => {
+                if check_placeholder(name, arg).is_err() {
+                    // Un formato inválido haría entrar en pánico a chrono
+                    rendered.push_str(&format!("{{{}}}", name));
+                    continue;
+                }
+                rendered.push_str(&render_placeholder(name, arg, context))
+            }
+        }
+    }
+    rendered
+}
+
+// Recorta un texto ya rellenado al máximo de bytes que acepta Discord, sin
+// partir caracteres
+pub fn fit_bytes(mut text: String, max_bytes: usize) -> String {
+    if text.len() > max_bytes {
+        let mut end = max_bytes;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+    text
+}
+
+// Completa con espacios de ancho cero un texto que se ha quedado por debajo
+// del mínimo de Discord al rellenarlo ({env.NOMBRE} vacía, un perfil de una
+// letra...). Discord cuenta caracteres, y cada uno ocupa al menos un byte
+pub fn pad_chars(mut text: String, min_chars: usize) -> String {
+    let missing = min_chars.saturating_sub(text.chars().count());
+    text.extend(std::iter::repeat_n('\u{200B}', missing));
+    text
+}
+
+pub fn fit_chars(text: String, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => text[..end].to_string(),
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> TemplateContext<'static> {
+        TemplateContext {
+            started_at: Instant::now(),
+            activity_index: 1,
+            activity_count: 3,
+            profile: "streaming",
+        }
+    }
+
+    #[test]
+    fn renders_placeholders() {
+        assert_eq!(
+            render("{activity_index}/{activity_count} en {profile}", &context()),
+            "2/3 en streaming"
+        );
+        assert_eq!(render("Abierto {uptime}", &context()), "Abierto 0m");
+        assert_eq!(render("sin marcadores", &context()), "sin marcadores");
+        assert_eq!(render("", &context()), "");
+    }
+
+    #[test]
+    fn time_and_date_formats() {
+        let time = render("{time}", &context());
+        assert_eq!(time.len(), 5);
+        assert_eq!(time.as_bytes()[2], b':');
+        assert_eq!(
+            render("{date:%Y}", &context()),
+            chrono::Local::now().format("%Y").to_string()
+        );
+        assert_eq!(check("{time:%H:%M:%S}"), Vec::<String>::new());
+        assert_eq!(check("{date:%Q}").len(), 1);
+        // Un formato inválido no llega a chrono
+        assert_eq!(render("{date:%Q}", &context()), "{date}");
+    }
+
+    #[test]
+    fn environment_variables() {
+        // cargo define CARGO_PKG_NAME al lanzar las pruebas; no se toca el
+        // entorno, que comparten todas las pruebas en paralelo
+        assert_eq!(
+            render("[{env.CARGO_PKG_NAME}]", &context()),
+            format!("[{}]", env!("CARGO_PKG_NAME"))
+        );
+        assert_eq!(render("[{env.LORIAN_TEMPLATE_MISSING}]", &context()), "[]");
+        assert_eq!(check("{env.1NOMBRE}").len(), 1);
+        assert_eq!(check("{env.NOMBRE:x}").len(), 1);
+    }
+
+    #[test]
+    fn escaped_braces() {
+        assert_eq!(render("{{time}}", &context()), "{time}");
+        assert_eq!(render("a }} b {{", &context()), "a } b {");
+        assert!(check("{{desconocido}}").is_empty());
+        assert!(!has_placeholders("{{time}}"));
+    }
+
+    #[test]
+    fn braces_that_are_not_placeholders() {
+        // Solo cuenta una llave seguida de una letra o "_"
+        for text in ["{ }", "{1}", "precio: {", "}", "${HOME}"] {
+            assert!(check(text).is_empty(), "{}", text);
+            assert_eq!(render(text, &context()), text);
+        }
+    }
+
+    #[test]
+    fn unclosed_placeholder() {
+        let errors = check("Hora: {time");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("falta la '}'"), "{}", errors[0]);
+        // Se deja tal cual al enviarla
+        assert_eq!(render("Hora: {time", &context()), "Hora: {time");
+        assert!(!has_placeholders("Hora: {time"));
+    }
+
+    #[test]
+    fn unknown_placeholders() {
+        let errors = check("{profile} {hora} {uptime:x}");
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].contains("desconocido {hora}"), "{}", errors[0]);
+        assert_eq!(render("{hora}", &context()), "{hora}");
+    }
+
+    #[test]
+    fn rendered_text_fits_discord_limits() {
+        // El texto cabe antes de rellenarlo pero no después
+        let context = TemplateContext {
+            profile: "streaming-largo",
+            ..context()
+        };
+        let template = format!("{}{{profile}}", "a".repeat(validate::MAX_TEXT_BYTES - 9));
+        assert_eq!(template.len(), validate::MAX_TEXT_BYTES);
+        let rendered = fit_bytes(render(&template, &context), validate::MAX_TEXT_BYTES);
+        assert_eq!(rendered.len(), validate::MAX_TEXT_BYTES);
+        assert!(rendered.ends_with("aastreaming"));
+
+        let activity = ActivityConfig {
+            details: template.clone(),
+            state: "ñ".repeat(100),
+            buttons: Some(vec![ButtonConfig {
+                label: format!("{{profile}} {}", "b".repeat(30)),
+                url: "https://example.com".to_string(),
+            }]),
+            ..Default::default()
+        };
+        let built = presence::build_activity(&activity, &context, (None, None));
+        assert_eq!(
+            built["details"].as_str().map(str::len),
+            Some(validate::MAX_TEXT_BYTES)
+        );
+        assert_eq!(
+            built["buttons"][0]["label"]
+                .as_str()
+                .map(|label| label.chars().count()),
+            Some(validate::MAX_BUTTON_LABEL_CHARS)
+        );
+    }
+
+    #[test]
+    fn short_rendered_text_is_padded() {
+        // Bien en la configuración, demasiado corto una vez relleno
+        let context = TemplateContext {
+            profile: "x",
+            ..context()
+        };
+        let activity = ActivityConfig {
+            details: "{profile}".to_string(),
+            state: "{env.LORIAN_TEST_NO_EXISTE}".to_string(),
+            ..Default::default()
+        };
+        let built = presence::build_activity(&activity, &context, (None, None));
+        assert_eq!(built["details"], "x\u{200B}");
+        assert_eq!(built["state"], "\u{200B}\u{200B}");
+        // Lo que ya llega al mínimo no se toca
+        assert_eq!(pad_chars("ab".to_string(), validate::MIN_TEXT_BYTES), "ab");
+    }
+
+    #[test]
+    fn fit_does_not_split_characters() {
+        // "ñ" ocupa 2 bytes
+        assert_eq!(fit_bytes("ññ".to_string(), 3), "ñ");
+        assert_eq!(fit_bytes("abc".to_string(), 3), "abc");
+        assert_eq!(fit_chars("ñañ".to_string(), 2), "ña");
+        assert_eq!(fit_chars("ab".to_string(), 5), "ab");
+    }
+}
@@ -0,0 +1,373 @@
+// Tiempo que muestra Discord debajo de la actividad. Antes se enviaba
+// start = ahora en cada rotación y el contador volvía a 00:00 cada pocos
+// segundos. Ahora cada actividad (o la sección del perfil para todas) elige:
+//   "none"                  sin contador
+//   "app_start"             desde que se abrió la app (por defecto)
+//   "profile_switch"        desde que se activó el perfil actual
+//   "activity_first_shown"  desde la primera vez que se mostró esta actividad
+//   {"start": "..."}        desde una fecha/hora fija
+//   {"end": "..."}          cuenta atrás hasta una fecha/hora fija o relativa
+//
+// Fechas/horas admitidas: RFC 3339 ("2025-03-01T18:00:00+01:00"),
+// "2025-03-01 18:00[:00]" y "18:00" en hora local. Solo "end" admite tiempos
+// relativos ("in 25m", "in 1h30m"), que empiezan a contar la primera vez que
+// se muestra la actividad y se mantienen entre rotaciones y reconexiones
+// hasta que terminan.
+
+use super::*;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use std::collections::HashMap;
+
+// Momento indicado en la configuración
+#[derive(Debug, PartialEq)]
+enum Point {
+    // Segundos Unix
+    At(i64),
+    // Segundos desde que se muestra la actividad
+    In(i64),
+}
+
+fn now_unix() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+// "25m", "1h30m", "90s", "2d"
+pub fn parse_duration(text: &str) -> StdResult<i64, String> {
+    let mut total = 0i64;
+    let mut number = String::new();
+    for c in text.chars().filter(|c| !c.is_whitespace()) {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            'd' => 86400,
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(format!("unidad '{}' desconocida (usa d, h, m o s)", c)),
+        };
+        if number.is_empty() {
+            return Err(format!("falta el número antes de '{}'", c));
+        }
+        let value: i64 = number
+            .parse()
+            .map_err(|_| format!("{}{} es demasiado tiempo", number, c))?;
+        total = total.saturating_add(value.saturating_mul(unit));
+        number.clear();
+    }
+    if !number.is_empty() {
+        return Err(format!(
+            "falta la unidad después de '{}' (d, h, m o s)",
+            number
+        ));
+    }
+    if total == 0 {
+        return Err("la duración debe ser mayor que 0".to_string());
+    }
+    Ok(total)
+}
+
+fn local_unix(datetime: NaiveDateTime) -> Option<i64> {
+    Local
+        .from_local_datetime(&datetime)
+        .earliest()
+        .map(|datetime| datetime.timestamp())
+}
+
+// is_end: una hora sin fecha ("18:00") es la próxima vez que llegue para una
+// cuenta atrás, y la última vez que pasó para un inicio
+fn parse_point(text: &str, is_end: bool) -> StdResult<Point, String> {
+    let text = text.trim();
+    if let Some(duration) = text.strip_prefix("in ") {
+        return parse_duration(duration).map(Point::In);
+    }
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(text) {
+        return Ok(Point::At(datetime.timestamp()));
+    }
+    for format in [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+    ] {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(text, format) {
+            return local_unix(datetime)
+                .map(Point::At)
+                .ok_or_else(|| format!("'{}' no existe en la zona horaria local", text));
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return local_unix(date.and_time(NaiveTime::MIN))
+            .map(Point::At)
+            .ok_or_else(|| format!("'{}' no existe en la zona horaria local", text));
+    }
+    for format in ["%H:%M:%S", "%H:%M"] {
+        if let Ok(time) = NaiveTime::parse_from_str(text, format) {
+            let today = Local::now().date_naive();
+            let now = now_unix();
+            let at = local_unix(today.and_time(time)).unwrap_or(now);
+            return Ok(Point::At(match (is_end, at <= now) {
+                (true, true) => at + 86400,
+                (false, false) => at - 86400,
+                _ => at,
+            }));
+        }
+    }
+    Err(format!(
+        "'{}' no es una fecha/hora válida (usa \"2025-03-01 18:00\", \"18:00\", RFC 3339{})",
+        text,
+        if is_end { " o \"in 25m\"" } else { "" }
+    ))
+}
+
+// Problemas de un modo, para la validación: errores y avisos
+pub fn check(mode: &TimestampMode) -> (Option<String>, Option<String>) {
+    let now = now_unix();
+    match mode {
+        TimestampMode::Start(text) => match parse_point(text, false) {
+            Ok(Point::In(_)) => (
+                Some(
+                    "start no admite tiempos relativos (\"in ...\"); usa end para una cuenta atrás"
+                        .to_string(),
+                ),
+                None,
+            ),
+            Ok(Point::At(at)) if at > now => (
+                None,
+                Some(format!(
+                    "'{}' es futuro: Discord mostrará el contador desde 00:00",
+                    text
+                )),
+            ),
+            Ok(_) => (None, None),
+            Err(message) => (Some(message), None),
+        },
+        TimestampMode::End(text) => match parse_point(text, true) {
+            Ok(Point::At(at)) if at <= now => (
+                None,
+                Some(format!(
+                    "'{}' ya ha pasado: no se mostrará la cuenta atrás",
+                    text
+                )),
+            ),
+            Ok(_) => (None, None),
+            Err(message) => (Some(message), None),
+        },
+        _ => (None, None),
+    }
+}
+
+// Cuenta atrás relativa en curso de una actividad
+#[derive(Debug)]
+struct Countdown {
+    // Texto de la configuración con el que se calculó, para empezar de nuevo
+    // si cambia al recargar
+    text: String,
+    end: i64,
+}
+
+// Momentos de referencia para los contadores. Vive en el gestor de la
+// presencia, así que sobrevive a rotaciones, reconexiones y recargas
+#[derive(Debug)]
+pub struct Clock {
+    app_started: i64,
+    profile_started: i64,
+    // Por nombre de actividad, dentro del perfil actual
+    first_shown: HashMap<String, i64>,
+    countdowns: HashMap<String, Countdown>,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        let now = now_unix();
+        Self {
+            app_started: now,
+            profile_started: now,
+            first_shown: HashMap::new(),
+            countdowns: HashMap::new(),
+        }
+    }
+
+    // Un perfil nuevo empieza todos sus contadores desde cero
+    pub fn profile_switched(&mut self) {
+        self.profile_started = now_unix();
+        self.first_shown.clear();
+        self.countdowns.clear();
+    }
+
+    // (start, end) a enviar para la actividad que se va a mostrar
+    pub fn timestamps(
+        &mut self,
+        activity: &str,
+        mode: &TimestampMode,
+    ) -> (Option<i64>, Option<i64>) {
+        let now = now_unix();
+        let first_shown = *self.first_shown.entry(activity.to_string()).or_insert(now);
+
+        match mode {
+            TimestampMode::None => (None, None),
+            TimestampMode::AppStart => (Some(self.app_started), None),
+            TimestampMode::ProfileSwitch => (Some(self.profile_started), None),
+            TimestampMode::ActivityFirstShown => (Some(first_shown), None),
+            // Un texto inválido no pasa la validación; por si acaso, sin contador
+            TimestampMode::Start(text) => match parse_point(text, false) {
+                Ok(Point::At(at)) => (Some(at), None),
+                _ => (None, None),
+            },
+            TimestampMode::End(text) => match parse_point(text, true) {
+                Ok(Point::At(at)) => (None, Some(at)),
+                Ok(Point::In(seconds)) => {
+                    let countdown =
+                        self.countdowns
+                            .entry(activity.to_string())
+                            .or_insert(Countdown {
+                                text: text.clone(),
+                                end: now + seconds,
+                            });
+                    // Terminada o cambiada en la configuración: empezar otra
+                    if countdown.text != *text || countdown.end <= now {
+                        countdown.text = text.clone();
+                        countdown.end = now + seconds;
+                    }
+                    (None, Some(countdown.end))
+                }
+                Err(_) => (None, None),
+            },
+        }
+    }
+}
+
+// Descripción corta para el log y el estado
+pub fn describe(mode: &TimestampMode) -> String {
+    match mode {
+        TimestampMode::None => "sin contador".to_string(),
+        TimestampMode::AppStart => "desde el inicio de la app".to_string(),
+        TimestampMode::ProfileSwitch => "desde el cambio de perfil".to_string(),
+        TimestampMode::ActivityFirstShown => "desde que se mostró por primera vez".to_string(),
+        TimestampMode::Start(text) => format!("desde {}", text),
+        TimestampMode::End(text) => format!("cuenta atrás hasta {}", text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("25m"), Ok(1500));
+        assert_eq!(parse_duration("1h30m"), Ok(5400));
+        assert_eq!(parse_duration("90s"), Ok(90));
+        assert_eq!(parse_duration("2d"), Ok(172800));
+        assert_eq!(parse_duration("1h 30m"), Ok(5400));
+        // Las unidades se suman aunque se repitan o vayan desordenadas
+        assert_eq!(parse_duration("30m1h"), Ok(5400));
+    }
+
+    #[test]
+    fn duration_needs_a_unit() {
+        // Un número suelto no es una duración: "announce Versión 2" tiene
+        // que seguir siendo un nombre
+        assert!(parse_duration("90").is_err());
+        assert!(parse_duration("1h30").is_err());
+    }
+
+    #[test]
+    fn invalid_durations() {
+        assert!(parse_duration("0s").is_err());
+        assert!(parse_duration("0h0m").is_err());
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("   ").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("10x").is_err());
+        assert!(parse_duration("abc").is_err());
+        assert!(parse_duration("-5m").is_err());
+        assert!(parse_duration("1.5h").is_err());
+        assert!(parse_duration("99999999999999999999s").is_err());
+    }
+
+    #[test]
+    fn relative_points() {
+        assert_eq!(parse_point("in 25m", true), Ok(Point::In(1500)));
+        assert_eq!(parse_point("  in 1h30m ", true), Ok(Point::In(5400)));
+        assert!(parse_point("in 0s", true).is_err());
+        assert!(parse_point("in", true).is_err());
+    }
+
+    #[test]
+    fn absolute_points() {
+        assert_eq!(
+            parse_point("2025-03-01T18:00:00+01:00", false),
+            Ok(Point::At(1740848400))
+        );
+        let local = local_unix(
+            NaiveDate::from_ymd_opt(2025, 3, 1)
+                .and_then(|date| date.and_hms_opt(18, 0, 0))
+                .expect("fecha válida"),
+        )
+        .expect("hora local");
+        for text in [
+            "2025-03-01 18:00",
+            "2025-03-01 18:00:00",
+            "2025-03-01T18:00",
+        ] {
+            assert_eq!(parse_point(text, false), Ok(Point::At(local)), "{}", text);
+        }
+        assert!(matches!(parse_point("2025-03-01", false), Ok(Point::At(_))));
+    }
+
+    #[test]
+    fn time_of_day_is_next_or_last() {
+        let now = now_unix();
+        // Una cuenta atrás va a la próxima vez que llegue esa hora y un
+        // inicio a la última vez que pasó
+        let Ok(Point::At(end)) = parse_point("12:00", true) else {
+            panic!("12:00 es una hora válida");
+        };
+        assert!(end > now && end <= now + 86400);
+        let Ok(Point::At(start)) = parse_point("12:00", false) else {
+            panic!("12:00 es una hora válida");
+        };
+        assert!(start <= now && start > now - 86400);
+    }
+
+    #[test]
+    fn invalid_points() {
+        for text in ["", "mañana", "25:00", "2025-13-01", "2025-03-01 18", "18h"] {
+            assert!(parse_point(text, true).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn check_modes() {
+        let start = |text: &str| TimestampMode::Start(text.to_string());
+        let end = |text: &str| TimestampMode::End(text.to_string());
+        assert_eq!(check(&end("in 25m")), (None, None));
+        assert!(check(&start("in 25m")).0.is_some());
+        assert!(check(&end("2000-01-01 00:00")).1.is_some());
+        assert!(check(&start("2999-01-01 00:00")).1.is_some());
+        assert!(check(&start("ayer")).0.is_some());
+        assert_eq!(check(&TimestampMode::AppStart), (None, None));
+    }
+
+    #[test]
+    fn relative_countdown_is_kept_across_rotations() {
+        let mut clock = Clock::new();
+        let mode = TimestampMode::End("in 25m".to_string());
+        let (_, first) = clock.timestamps("a", &mode);
+        assert_eq!(clock.timestamps("a", &mode), (None, first));
+        // Si cambia en la configuración empieza de nuevo
+        let (_, changed) = clock.timestamps("a", &TimestampMode::End("in 1h".to_string()));
+        assert!(changed > first);
+    }
+}
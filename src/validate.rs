@@ -0,0 +1,1411 @@
+// Validación de la configuración: además de los errores de sintaxis de
+// serde, comprueba los límites que Discord aplica a la presencia. Discord no
+// avisa cuando algo se sale de sus límites, simplemente ignora la actividad,
+// así que es mejor rechazarla antes de enviarla.
+//
+// Se listan TODOS los problemas encontrados (no solo el primero), cada uno
+// con la actividad, el campo y, en JSON, la línea/columna.
+
+use super::*;
+use std::collections::HashMap;
+use std::fmt;
+
+// Límites documentados de Discord para SET_ACTIVITY
+const MAX_BUTTONS: usize = 2;
+pub const MAX_BUTTON_LABEL_CHARS: usize = 32;
+const MAX_BUTTON_URL_CHARS: usize = 512;
+pub const MIN_TEXT_BYTES: usize = 2;
+pub const MAX_TEXT_BYTES: usize = 128;
+const MAX_PARTY_ID_BYTES: usize = 128;
+const MAX_FIELD_URL_CHARS: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    // Archivo incluido donde está el problema; None si es el principal
+    pub file: Option<PathBuf>,
+    // Nombre de la actividad afectada, si el problema es de una actividad
+    pub activity: Option<String>,
+    // Ruta del campo, p. ej. discord.activities[2].buttons[0].label
+    pub field: String,
+    pub message: String,
+    // Línea y columna (empezando en 1) dentro del archivo
+    pub location: Option<(usize, usize)>,
+}
+
+impl Diagnostic {
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    // Formato tipo compilador: config.json:12:5: error [commission] campo: mensaje
+    // file es el archivo principal; los incluidos se muestran relativos a él
+    pub fn display_with_file(&self, file: &Path) -> String {
+        let file_name = match &self.file {
+            Some(included) => relative_display(included, file),
+            None => file
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| file.display().to_string()),
+        };
+        match self.location {
+            Some((line, column)) => format!("{}:{}:{}: {}", file_name, line, column, self),
+            None => format!("{}: {}", file_name, self),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "aviso",
+        };
+        write!(f, "{}", severity)?;
+        if let Some(activity) = &self.activity {
+            write!(f, " [{}]", activity)?;
+        }
+        write!(f, " {}: {}", self.field, self.message)
+    }
+}
+
+type Locations = HashMap<String, (usize, usize)>;
+
+// Actividades leídas de un archivo, para las comprobaciones entre archivos
+struct ActivitySource {
+    // None: el archivo de configuración principal
+    file: Option<PathBuf>,
+    // Ruta de la lista dentro del archivo (discord.activities, activities o
+    // profiles.<perfil>.activities)
+    prefix: String,
+    activities: Vec<ActivityConfig>,
+    locations: Locations,
+}
+
+// Parsea un archivo en el formato indicado. activities_path es dónde está la
+// lista de actividades dentro del archivo, para poder señalar cada una si la
+// estructura no encaja. Con expand se sustituyen las variables de entorno
+// (ver expand.rs); los errores de sustitución van en la lista devuelta.
+fn parse_source<T: serde::de::DeserializeOwned>(
+    source: &str,
+    format: formats::ConfigFormat,
+    activities_path: &[&str],
+    expand: bool,
+) -> StdResult<(T, Locations, Vec<Diagnostic>), Vec<Diagnostic>> {
+    let value = format.parse(source).map_err(|error| {
+        vec![Diagnostic {
+            severity: Severity::Error,
+            file: None,
+            activity: None,
+            field: "(sintaxis)".to_string(),
+            message: error.message,
+            location: error.location,
+        }]
+    })?;
+
+    // Las posiciones de cada campo solo se calculan para JSON; en el resto
+    // de formatos los diagnósticos llevan el campo pero no la línea
+    let is_json = format == formats::ConfigFormat::Json;
+    let locations = if is_json {
+        locate_values(source).unwrap_or_default()
+    } else {
+        HashMap::new()
+    };
+
+    let parsed = if is_json {
+        serde_json::from_str::<T>(source)
+    } else {
+        serde_json::from_value::<T>(value.clone())
+    };
+    let parsed = match parsed {
+        Ok(parsed) => parsed,
+        Err(e) => return Err(type_diagnostics(&value, activities_path, &locations, e)),
+    };
+    if !expand {
+        return Ok((parsed, locations, Vec::new()));
+    }
+
+    // La sustitución solo cambia textos, así que los tipos ya se comprobaron
+    // arriba (en JSON con su línea y columna)
+    let mut expanded = value;
+    let errors = expand::expand_value(&mut expanded);
+    expand::apply_overrides(&mut expanded);
+    let parsed = serde_json::from_value::<T>(expanded).unwrap_or(parsed);
+    let diagnostics = errors
+        .into_iter()
+        .map(|error| Diagnostic {
+            severity: Severity::Error,
+            file: None,
+            activity: None,
+            location: locations.get(&error.field).copied(),
+            field: error.field,
+            message: error.message,
+        })
+        .collect();
+    Ok((parsed, locations, diagnostics))
+}
+
+fn check_main_source(
+    source: &str,
+    format: formats::ConfigFormat,
+    expand: bool,
+) -> (Option<(Config, Locations)>, Vec<Diagnostic>) {
+    match parse_source::<Config>(source, format, &["discord", "activities"], expand) {
+        Ok((config, locations, mut diagnostics)) => {
+            diagnostics.extend(validate_settings(&config, &locations));
+            diagnostics.extend(validate_activities(
+                "discord.activities",
+                &config.discord.activities,
+                &locations,
+            ));
+            diagnostics.extend(validate_profiles(&config, &locations));
+            (Some((config, locations)), diagnostics)
+        }
+        Err(diagnostics) => (None, diagnostics),
+    }
+}
+
+// Parsea y valida el contenido de un archivo de configuración, sin resolver
+// sus includes. Devuelve la configuración si se pudo deserializar (aunque
+// tenga errores semánticos) y todos los problemas encontrados.
+//
+// Sin expand las variables ${VAR} se quedan como están: es lo que se usa
+// para reescribir el archivo (setup, convert) sin perderlas.
+pub fn check_config_source(
+    source: &str,
+    format: formats::ConfigFormat,
+    expand: bool,
+) -> (Option<Config>, Vec<Diagnostic>) {
+    let (parsed, diagnostics) = check_main_source(source, format, expand);
+    (parsed.map(|(config, _)| config), diagnostics)
+}
+
+fn check_activities_file(path: &Path, expand: bool) -> (Option<ActivitySource>, Vec<Diagnostic>) {
+    let error = |field: &str, message: String| Diagnostic {
+        severity: Severity::Error,
+        file: Some(path.to_path_buf()),
+        activity: None,
+        field: field.to_string(),
+        message,
+        location: None,
+    };
+
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            return (
+                None,
+                vec![error("(archivo)", format!("no se pudo leer: {}", e))],
+            )
+        }
+    };
+    let format = formats::ConfigFormat::from_path(path).unwrap_or(formats::ConfigFormat::Json);
+
+    let (parsed, diagnostics) =
+        match parse_source::<includes::ActivitiesFile>(&source, format, &["activities"], expand) {
+            Ok((parsed, locations, mut diagnostics)) => {
+                diagnostics.extend(validate_activities(
+                    "activities",
+                    &parsed.activities,
+                    &locations,
+                ));
+                let source = ActivitySource {
+                    file: Some(path.to_path_buf()),
+                    prefix: "activities".to_string(),
+                    activities: parsed.activities,
+                    locations,
+                };
+                (Some(source), diagnostics)
+            }
+            Err(diagnostics) => (None, diagnostics),
+        };
+
+    let diagnostics = diagnostics
+        .into_iter()
+        .map(|diagnostic| Diagnostic {
+            file: Some(path.to_path_buf()),
+            ..diagnostic
+        })
+        .collect();
+    (parsed, diagnostics)
+}
+
+// Lee y valida un archivo de configuración junto con todo lo que incluye.
+// La configuración devuelta lleva las actividades ya combinadas en el orden
+// de rotación. Solo falla si no se puede leer el archivo principal.
+//
+// Sin expand las variables ${VAR} se quedan como están, también en los
+// archivos incluidos: es lo que se guarda en config.last-good.json para no
+// dejar en claro los valores que vienen del entorno.
+pub fn check_config_file(
+    config_path: &Path,
+    expand: bool,
+) -> StdResult<(Option<Config>, Vec<Diagnostic>), String> {
+    let source = fs::read_to_string(config_path)
+        .map_err(|e| format!("Error leyendo {}: {}", config_path.display(), e))?;
+    let format =
+        formats::ConfigFormat::from_path(config_path).unwrap_or(formats::ConfigFormat::Json);
+
+    let (parsed, mut diagnostics) = check_main_source(&source, format, expand);
+    let Some((mut config, locations)) = parsed else {
+        return Ok((None, diagnostics));
+    };
+
+    let (files, errors) = includes::resolve(config_path, &config.include);
+    for error in errors {
+        let field = format!("include[{}]", error.index);
+        diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            file: None,
+            activity: None,
+            location: locations.get(&field).copied(),
+            field,
+            message: error.message,
+        });
+    }
+
+    let mut sources = vec![ActivitySource {
+        file: None,
+        prefix: "discord.activities".to_string(),
+        activities: std::mem::take(&mut config.discord.activities),
+        locations,
+    }];
+    for file in files {
+        let (source, file_diagnostics) = check_activities_file(&file, expand);
+        diagnostics.extend(file_diagnostics);
+        sources.extend(source);
+    }
+
+    diagnostics.extend(validate_merged(&sources, config_path));
+    let locations = sources[0].locations.clone();
+    config.discord.activities = sources
+        .into_iter()
+        .flat_map(|source| source.activities)
+        .collect();
+    diagnostics.extend(validate_rotation("discord", &config.discord, &locations));
+    diagnostics.extend(validate_schedules("discord", &config.discord, &locations));
+    diagnostics.extend(validate_reconnect("discord", &config.discord, &locations));
+    Ok((Some(config), diagnostics))
+}
+
+// Cuando la estructura no encaja, serde solo informa del primer fallo. Se
+// repite la deserialización actividad por actividad para poder listar todos
+// los campos con tipos incorrectos o que faltan.
+fn type_diagnostics(
+    value: &serde_json::Value,
+    activities_path: &[&str],
+    locations: &Locations,
+    error: serde_json::Error,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let prefix = activities_path.join(".");
+
+    if let Some(activities) = activities_path
+        .iter()
+        .try_fold(value, |value, key| value.get(key))
+        .and_then(|activities| activities.as_array())
+    {
+        for (index, activity) in activities.iter().enumerate() {
+            if let Err(e) = serde_json::from_value::<ActivityConfig>(activity.clone()) {
+                let field = format!("{}[{}]", prefix, index);
+                diagnostics.push(Diagnostic {
+                    severity: Severity::Error,
+                    file: None,
+                    activity: activity
+                        .get("name")
+                        .and_then(|name| name.as_str())
+                        .map(|name| name.to_string()),
+                    location: locations.get(&field).copied(),
+                    field,
+                    message: e.to_string(),
+                });
+            }
+        }
+    }
+
+    // El error original está fuera de las actividades (app_id, discord...).
+    // Los errores de from_value no tienen posición (línea 0)
+    if diagnostics.is_empty() {
+        diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            file: None,
+            activity: None,
+            field: "(estructura)".to_string(),
+            message: serde_message(&error),
+            location: (error.line() > 0).then(|| (error.line(), error.column())),
+        });
+    }
+
+    diagnostics
+}
+
+// Mensaje de serde sin el " at line X column Y" final (ya va en la posición)
+fn serde_message(error: &serde_json::Error) -> String {
+    let message = error.to_string();
+    match message.rfind(" at line ") {
+        Some(index) => message[..index].to_string(),
+        None => message,
+    }
+}
+
+pub fn is_valid_snowflake(id: &str) -> bool {
+    (17..=20).contains(&id.len())
+        && id.chars().all(|c| c.is_ascii_digit())
+        && id.parse::<u64>().is_ok()
+}
+
+pub fn is_valid_button_url(url: &str) -> bool {
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"));
+    match rest {
+        Some(rest) => {
+            let host = rest.split(['/', '?', '#']).next().unwrap_or("");
+            !host.is_empty() && !url.chars().any(char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+// Fechas/horas de los contadores (ver timestamps.rs)
+fn timestamp_diagnostics(mode: &TimestampMode) -> Vec<(Severity, String)> {
+    let (error, warning) = timestamps::check(mode);
+    error
+        .map(|message| (Severity::Error, message))
+        .into_iter()
+        .chain(warning.map(|message| (Severity::Warning, message)))
+        .collect()
+}
+
+// Campos generales del archivo principal
+fn validate_settings(config: &Config, locations: &Locations) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut push = |severity: Severity, activity: Option<&str>, field: String, message: String| {
+        diagnostics.push(Diagnostic {
+            severity,
+            file: None,
+            activity: activity.map(|name| name.to_string()),
+            location: locations.get(&field).copied(),
+            field,
+            message,
+        });
+    };
+
+    if config.version > migrate::CONFIG_VERSION {
+        push(
+            Severity::Error,
+            None,
+            "version".to_string(),
+            format!(
+                "versión {} del esquema - esta versión de la app solo entiende hasta la {}",
+                config.version,
+                migrate::CONFIG_VERSION
+            ),
+        );
+    } else if config.version < migrate::CONFIG_VERSION {
+        push(
+            Severity::Warning,
+            None,
+            "version".to_string(),
+            format!(
+                "versión {} del esquema - se actualizará a la {} automáticamente al cargarlo",
+                config.version,
+                migrate::CONFIG_VERSION
+            ),
+        );
+    }
+
+    let sections = std::iter::once(("discord".to_string(), &config.discord)).chain(
+        config
+            .profiles
+            .iter()
+            .map(|(name, profile)| (format!("profiles.{}", name), profile)),
+    );
+    for (section, discord) in sections {
+        let field = format!("{}.app_id", section);
+        if !is_valid_snowflake(&discord.app_id) {
+            let origin = if section == "discord"
+                && expand::app_id_override().as_deref() == Some(discord.app_id.as_str())
+            {
+                format!(" (viene de {})", expand::APP_ID_OVERRIDE)
+            } else {
+                String::new()
+            };
+            push(
+                Severity::Error,
+                None,
+                field.clone(),
+                format!(
+                    "'{}'{} no es un Application ID válido (debe ser un número de 17 a 20 dígitos)",
+                    discord.app_id, origin
+                ),
+            );
+        }
+
+        if let Some(mode) = &discord.timestamps {
+            for (severity, message) in timestamp_diagnostics(mode) {
+                push(severity, None, format!("{}.timestamps", section), message);
+            }
+        }
+
+        if is_placeholder_app_id(&discord.app_id) {
+            push(
+                Severity::Warning,
+                None,
+                field,
+                "es el Application ID de ejemplo - ejecuta `lorianworkspace setup` para configurar el tuyo"
+                    .to_string(),
+            );
+        }
+    }
+
+    if config.profiles.contains_key(profiles::MAIN_PROFILE) {
+        push(
+            Severity::Error,
+            None,
+            format!("profiles.{}", profiles::MAIN_PROFILE),
+            format!(
+                "'{}' es el nombre del perfil de la sección discord - usa otro nombre",
+                profiles::MAIN_PROFILE
+            ),
+        );
+    }
+
+    if let Some(name) = &config.default_profile {
+        if profiles::profile(config, name).is_none() {
+            push(
+                Severity::Error,
+                None,
+                "default_profile".to_string(),
+                format!(
+                    "no existe el perfil '{}' (disponibles: {})",
+                    name,
+                    profiles::profile_names(config).join(", ")
+                ),
+            );
+        }
+    }
+
+    diagnostics
+}
+
+// Actividades de los perfiles con nombre. Cada perfil rota solo las suyas,
+// así que los nombres solo tienen que ser únicos dentro del perfil
+fn validate_profiles(config: &Config, locations: &Locations) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for (name, profile) in &config.profiles {
+        let source = ActivitySource {
+            file: None,
+            prefix: format!("profiles.{}.activities", name),
+            activities: profile.activities.clone(),
+            locations: locations.clone(),
+        };
+        diagnostics.extend(validate_activities(
+            &source.prefix,
+            &source.activities,
+            locations,
+        ));
+        // Sin archivos incluidos la ruta del principal no se usa
+        diagnostics.extend(validate_merged(
+            std::slice::from_ref(&source),
+            Path::new(""),
+        ));
+        let section = format!("profiles.{}", name);
+        diagnostics.extend(validate_rotation(&section, profile, locations));
+        diagnostics.extend(validate_schedules(&section, profile, locations));
+        diagnostics.extend(validate_reconnect(&section, profile, locations));
+    }
+    diagnostics
+}
+
+// Orden de rotación de una sección. Necesita la lista completa de
+// actividades, con las de los archivos incluidos
+fn validate_rotation(
+    section: &str,
+    discord: &DiscordConfig,
+    locations: &Locations,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut push = |severity: Severity, field: String, message: String| {
+        diagnostics.push(Diagnostic {
+            severity,
+            file: None,
+            activity: None,
+            location: locations.get(&field).copied(),
+            field,
+            message,
+        });
+    };
+
+    let mode = discord.rotation.clone().unwrap_or_default();
+    match &mode {
+        RotationMode::Playlist(names) if names.is_empty() => push(
+            Severity::Error,
+            format!("{}.rotation.playlist", section),
+            "la lista no puede estar vacía".to_string(),
+        ),
+        RotationMode::Playlist(names) => {
+            for (index, name) in names.iter().enumerate() {
+                if !discord
+                    .activities
+                    .iter()
+                    .any(|activity| &activity.name == name)
+                {
+                    push(
+                        Severity::Error,
+                        format!("{}.rotation.playlist[{}]", section, index),
+                        format!("no existe ninguna actividad llamada '{}'", name),
+                    );
+                }
+            }
+        }
+        RotationMode::Weighted
+            if !discord.activities.is_empty()
+                && discord
+                    .activities
+                    .iter()
+                    .all(|activity| rotation::weight(activity) == 0) =>
+        {
+            push(
+                Severity::Error,
+                format!("{}.rotation", section),
+                "todas las actividades tienen weight 0".to_string(),
+            )
+        }
+        _ => {}
+    }
+
+    if mode != RotationMode::Weighted
+        && discord
+            .activities
+            .iter()
+            .any(|activity| activity.weight.is_some())
+    {
+        push(
+            Severity::Warning,
+            format!("{}.rotation", section),
+            format!(
+                "weight solo se usa con la rotación \"weighted\" (ahora es {})",
+                rotation::describe(&mode)
+            ),
+        );
+    }
+    diagnostics
+}
+
+// Esperas entre reintentos de conexión
+fn validate_reconnect(
+    section: &str,
+    discord: &DiscordConfig,
+    locations: &Locations,
+) -> Vec<Diagnostic> {
+    let Some(reconnect) = &discord.reconnect else {
+        return Vec::new();
+    };
+    let mut diagnostics = Vec::new();
+    let mut push = |field: &str, message: String| {
+        let field = format!("{}.reconnect.{}", section, field);
+        diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            file: None,
+            activity: None,
+            location: locations.get(&field).copied(),
+            field,
+            message,
+        });
+    };
+
+    if reconnect.initial_delay_seconds == 0 {
+        push("initial_delay_seconds", "debe ser mayor que 0".to_string());
+    }
+    if reconnect.max_delay_seconds < reconnect.initial_delay_seconds {
+        push(
+            "max_delay_seconds",
+            format!(
+                "debe ser mayor o igual que initial_delay_seconds ({})",
+                reconnect.initial_delay_seconds
+            ),
+        );
+    }
+    diagnostics
+}
+
+// Horarios de una sección y los nombres de horario que usan sus actividades
+// (que pueden venir de archivos incluidos)
+fn validate_schedules(
+    section: &str,
+    discord: &DiscordConfig,
+    locations: &Locations,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut push = |activity: Option<&str>, field: String, message: String| {
+        diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            file: None,
+            activity: activity.map(|name| name.to_string()),
+            location: locations.get(&field).copied(),
+            field,
+            message,
+        });
+    };
+
+    for (name, schedule) in &discord.schedules {
+        for message in schedule::check(schedule) {
+            push(None, format!("{}.schedules.{}", section, name), message);
+        }
+    }
+    if let Some(quiet_hours) = &discord.quiet_hours {
+        for message in schedule::check(quiet_hours) {
+            push(None, format!("{}.quiet_hours", section), message);
+        }
+    }
+    for (index, activity) in discord.activities.iter().enumerate() {
+        if let Some(ScheduleRef::Named(name)) = &activity.schedule {
+            if !discord.schedules.contains_key(name) {
+                let available: Vec<&str> = discord.schedules.keys().map(String::as_str).collect();
+                push(
+                    Some(&activity.name),
+                    format!("{}.activities[{}].schedule", section, index),
+                    format!(
+                        "no existe el horario '{}' en {}.schedules (disponibles: {})",
+                        name,
+                        section,
+                        if available.is_empty() {
+                            "ninguno".to_string()
+                        } else {
+                            available.join(", ")
+                        }
+                    ),
+                );
+            }
+        }
+    }
+    diagnostics
+}
+
+// Comprobaciones de cada actividad por separado
+fn validate_activities(
+    prefix: &str,
+    activities: &[ActivityConfig],
+    locations: &Locations,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut push = |severity: Severity, activity: Option<&str>, field: String, message: String| {
+        diagnostics.push(Diagnostic {
+            severity,
+            file: None,
+            activity: activity.map(|name| name.to_string()),
+            location: locations.get(&field).copied(),
+            field,
+            message,
+        });
+    };
+
+    for (index, activity) in activities.iter().enumerate() {
+        let base = format!("{}[{}]", prefix, index);
+        let name = Some(activity.name.as_str());
+
+        if activity.name.trim().is_empty() {
+            push(
+                Severity::Error,
+                None,
+                format!("{}.name", base),
+                "el nombre de la actividad no puede estar vacío".to_string(),
+            );
+        }
+
+        // Textos con plantillas: se comprueban los marcadores. La longitud
+        // depende de lo que salga al rellenarlos (al enviar se recorta o se
+        // completa hasta los límites)
+        let mut templated = vec![
+            ("details".to_string(), &activity.details),
+            ("state".to_string(), &activity.state),
+        ];
+        for (field, value) in [
+            ("large_text", &activity.large_text),
+            ("small_text", &activity.small_text),
+        ] {
+            if let Some(value) = value {
+                templated.push((field.to_string(), value));
+            }
+        }
+        for (button_index, button) in activity.buttons.iter().flatten().enumerate() {
+            templated.push((format!("buttons[{}].label", button_index), &button.label));
+        }
+        for (field, value) in &templated {
+            for message in templates::check(value) {
+                push(
+                    Severity::Error,
+                    name,
+                    format!("{}.{}", base, field),
+                    message,
+                );
+            }
+        }
+
+        for (field, value) in [("details", &activity.details), ("state", &activity.state)] {
+            if templates::has_placeholders(value) {
+                continue;
+            }
+            if value.len() < MIN_TEXT_BYTES || value.len() > MAX_TEXT_BYTES {
+                push(
+                    Severity::Error,
+                    name,
+                    format!("{}.{}", base, field),
+                    format!(
+                        "debe tener entre {} y {} bytes (tiene {})",
+                        MIN_TEXT_BYTES,
+                        MAX_TEXT_BYTES,
+                        value.len()
+                    ),
+                );
+            }
+        }
+
+        for (field, value) in [
+            ("large_text", &activity.large_text),
+            ("small_text", &activity.small_text),
+        ] {
+            if let Some(value) = value.as_ref().filter(|v| !templates::has_placeholders(v)) {
+                if value.len() < MIN_TEXT_BYTES || value.len() > MAX_TEXT_BYTES {
+                    push(
+                        Severity::Error,
+                        name,
+                        format!("{}.{}", base, field),
+                        format!(
+                            "debe tener entre {} y {} bytes (tiene {})",
+                            MIN_TEXT_BYTES,
+                            MAX_TEXT_BYTES,
+                            value.len()
+                        ),
+                    );
+                }
+            }
+        }
+
+        if activity.duration_seconds == 0 {
+            push(
+                Severity::Error,
+                name,
+                format!("{}.duration_seconds", base),
+                "debe ser mayor que 0".to_string(),
+            );
+        } else if activity.duration_seconds < ratelimit::MIN_INTERVAL_SECONDS {
+            // Discord no acepta cambios tan seguidos: la siguiente espera
+            push(
+                Severity::Warning,
+                name,
+                format!("{}.duration_seconds", base),
+                format!(
+                    "{}s es menos de lo que permite Discord ({} cambios cada {}s, uno cada {}s de media): la actividad puede verse más tiempo",
+                    activity.duration_seconds,
+                    ratelimit::MAX_UPDATES,
+                    ratelimit::WINDOW.as_secs(),
+                    ratelimit::MIN_INTERVAL_SECONDS
+                ),
+            );
+        }
+
+        // Sin imagen grande no se envían assets, así que el resto se ignora
+        if activity.large_image.is_none() {
+            for (field, value) in [
+                ("large_text", &activity.large_text),
+                ("small_image", &activity.small_image),
+            ] {
+                if value.is_some() {
+                    push(
+                        Severity::Warning,
+                        name,
+                        format!("{}.{}", base, field),
+                        "se ignora porque no hay large_image".to_string(),
+                    );
+                }
+            }
+        }
+        if activity.small_image.is_none() && activity.small_text.is_some() {
+            push(
+                Severity::Warning,
+                name,
+                format!("{}.small_text", base),
+                "se ignora porque no hay small_image".to_string(),
+            );
+        }
+
+        if let Some(party) = &activity.party {
+            if party.max == 0 || party.current > party.max {
+                push(
+                    Severity::Error,
+                    name,
+                    format!("{}.party", base),
+                    format!(
+                        "el grupo debe tener max >= 1 y current <= max (es {} de {})",
+                        party.current, party.max
+                    ),
+                );
+            }
+            if let Some(id) = &party.id {
+                if id.is_empty() || id.len() > MAX_PARTY_ID_BYTES {
+                    push(
+                        Severity::Error,
+                        name,
+                        format!("{}.party.id", base),
+                        format!(
+                            "debe tener entre 1 y {} bytes (tiene {})",
+                            MAX_PARTY_ID_BYTES,
+                            id.len()
+                        ),
+                    );
+                }
+            }
+        }
+
+        if let Some(mode) = &activity.timestamps {
+            for (severity, message) in timestamp_diagnostics(mode) {
+                push(severity, name, format!("{}.timestamps", base), message);
+            }
+        }
+
+        if let Some(ScheduleRef::Inline(schedule)) = &activity.schedule {
+            for message in schedule::check(schedule) {
+                push(Severity::Error, name, format!("{}.schedule", base), message);
+            }
+        }
+
+        // Enlaces de los textos y de las imágenes
+        for (field, url) in [
+            ("details_url", &activity.details_url),
+            ("state_url", &activity.state_url),
+            ("large_url", &activity.large_url),
+            ("small_url", &activity.small_url),
+        ] {
+            let Some(url) = url else {
+                continue;
+            };
+            if !is_valid_button_url(url) {
+                push(
+                    Severity::Error,
+                    name,
+                    format!("{}.{}", base, field),
+                    format!("'{}' no es una URL http(s) válida", url),
+                );
+            } else if url.chars().count() > MAX_FIELD_URL_CHARS {
+                push(
+                    Severity::Error,
+                    name,
+                    format!("{}.{}", base, field),
+                    format!("la URL supera los {} caracteres", MAX_FIELD_URL_CHARS),
+                );
+            }
+        }
+        for (field, url, image, image_name) in [
+            (
+                "large_url",
+                &activity.large_url,
+                &activity.large_image,
+                "large_image",
+            ),
+            (
+                "small_url",
+                &activity.small_url,
+                &activity.small_image,
+                "small_image",
+            ),
+        ] {
+            if url.is_some() && image.is_none() {
+                push(
+                    Severity::Warning,
+                    name,
+                    format!("{}.{}", base, field),
+                    format!("se ignora porque no hay {}", image_name),
+                );
+            }
+        }
+
+        if let Some(buttons) = &activity.buttons {
+            if buttons.len() > MAX_BUTTONS {
+                push(
+                    Severity::Error,
+                    name,
+                    format!("{}.buttons", base),
+                    format!(
+                        "Discord admite como máximo {} botones (hay {})",
+                        MAX_BUTTONS,
+                        buttons.len()
+                    ),
+                );
+            }
+
+            for (button_index, button) in buttons.iter().enumerate() {
+                let button_base = format!("{}.buttons[{}]", base, button_index);
+                let label_chars = button.label.chars().count();
+                let too_long = label_chars > MAX_BUTTON_LABEL_CHARS
+                    && !templates::has_placeholders(&button.label);
+                if label_chars == 0 || too_long {
+                    push(
+                        Severity::Error,
+                        name,
+                        format!("{}.label", button_base),
+                        format!(
+                            "la etiqueta debe tener entre 1 y {} caracteres (tiene {})",
+                            MAX_BUTTON_LABEL_CHARS, label_chars
+                        ),
+                    );
+                }
+                if !is_valid_button_url(&button.url) {
+                    push(
+                        Severity::Error,
+                        name,
+                        format!("{}.url", button_base),
+                        format!("'{}' no es una URL http(s) válida", button.url),
+                    );
+                } else if button.url.chars().count() > MAX_BUTTON_URL_CHARS {
+                    push(
+                        Severity::Error,
+                        name,
+                        format!("{}.url", button_base),
+                        format!("la URL supera los {} caracteres", MAX_BUTTON_URL_CHARS),
+                    );
+                }
+            }
+        }
+    }
+
+    diagnostics
+}
+
+// Errores de una actividad suelta (la de un override), sin posiciones
+pub fn check_activity(activity: &ActivityConfig) -> Vec<String> {
+    validate_activities(
+        "activity",
+        std::slice::from_ref(activity),
+        &Locations::new(),
+    )
+    .into_iter()
+    .filter(Diagnostic::is_error)
+    .map(|diagnostic| {
+        let field = diagnostic.field.trim_start_matches("activity[0].");
+        format!("{}: {}", field, diagnostic.message)
+    })
+    .collect()
+}
+
+// Cómo mostrar un archivo incluido: relativo a la carpeta del principal
+fn relative_display(file: &Path, config_path: &Path) -> String {
+    config_path
+        .parent()
+        .and_then(|base| file.strip_prefix(base).ok())
+        .unwrap_or(file)
+        .display()
+        .to_string()
+}
+
+// Comprobaciones sobre la lista combinada de todos los archivos
+fn validate_merged(sources: &[ActivitySource], config_path: &Path) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    if sources.iter().all(|source| source.activities.is_empty()) {
+        let first = sources.first();
+        let field = first
+            .map(|source| source.prefix.clone())
+            .unwrap_or_else(|| "discord.activities".to_string());
+        diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            file: None,
+            activity: None,
+            location: first.and_then(|source| source.locations.get(&field).copied()),
+            field,
+            message: "no hay actividades configuradas".to_string(),
+        });
+    }
+
+    // Los nombres son únicos entre todos los archivos: con ellos se
+    // seleccionan las actividades
+    let mut seen_names: HashMap<&str, String> = HashMap::new();
+    for source in sources {
+        for (index, activity) in source.activities.iter().enumerate() {
+            if activity.name.trim().is_empty() {
+                continue;
+            }
+            let entry = format!("{}[{}]", source.prefix, index);
+            match seen_names.get(activity.name.as_str()) {
+                Some(first) => {
+                    let field = format!("{}.name", entry);
+                    diagnostics.push(Diagnostic {
+                        severity: Severity::Error,
+                        file: source.file.clone(),
+                        activity: Some(activity.name.clone()),
+                        location: source.locations.get(&field).copied(),
+                        field,
+                        message: format!("nombre duplicado (ya lo usa {})", first),
+                    });
+                }
+                None => {
+                    let place = match &source.file {
+                        Some(file) => format!("{} {}", relative_display(file, config_path), entry),
+                        None => entry,
+                    };
+                    seen_names.insert(&activity.name, place);
+                }
+            }
+        }
+    }
+
+    diagnostics
+}
+
+// Localizador de posiciones: recorre el JSON y anota la línea/columna donde
+// empieza cada valor, indexado por su ruta (discord.activities[0].name).
+// serde_json no conserva posiciones tras parsear, por eso hace falta esto.
+struct Locator<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+    column: usize,
+    locations: HashMap<String, (usize, usize)>,
+}
+
+impl Locator<'_> {
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(c) if c.is_whitespace()) {
+            self.bump();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> StdResult<(), ()> {
+        self.skip_whitespace();
+        if self.bump() == Some(expected) {
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+
+    fn string(&mut self) -> StdResult<String, ()> {
+        self.expect('"')?;
+        let mut value = String::new();
+        loop {
+            match self.bump().ok_or(())? {
+                '"' => return Ok(value),
+                '\\' => {
+                    // Las rutas solo usan claves simples; basta con no
+                    // confundir una comilla escapada con el final
+                    let escaped = self.bump().ok_or(())?;
+                    value.push(escaped);
+                }
+                c => value.push(c),
+            }
+        }
+    }
+
+    fn value(&mut self, path: String) -> StdResult<(), ()> {
+        self.skip_whitespace();
+        self.locations
+            .insert(path.clone(), (self.line, self.column));
+
+        match self.peek().ok_or(())? {
+            '{' => {
+                self.bump();
+                self.skip_whitespace();
+                if self.peek() == Some('}') {
+                    self.bump();
+                    return Ok(());
+                }
+                loop {
+                    let key = self.string()?;
+                    self.expect(':')?;
+                    let child = if path.is_empty() {
+                        key
+                    } else {
+                        format!("{}.{}", path, key)
+                    };
+                    self.value(child)?;
+                    self.skip_whitespace();
+                    match self.bump().ok_or(())? {
+                        ',' => continue,
+                        '}' => return Ok(()),
+                        _ => return Err(()),
+                    }
+                }
+            }
+            '[' => {
+                self.bump();
+                self.skip_whitespace();
+                if self.peek() == Some(']') {
+                    self.bump();
+                    return Ok(());
+                }
+                let mut index = 0;
+                loop {
+                    self.value(format!("{}[{}]", path, index))?;
+                    index += 1;
+                    self.skip_whitespace();
+                    match self.bump().ok_or(())? {
+                        ',' => continue,
+                        ']' => return Ok(()),
+                        _ => return Err(()),
+                    }
+                }
+            }
+            '"' => self.string().map(|_| ()),
+            _ => {
+                // Números, true, false, null
+                while matches!(self.peek(), Some(c) if !c.is_whitespace() && !",]}".contains(c)) {
+                    self.bump();
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn locate_values(source: &str) -> StdResult<HashMap<String, (usize, usize)>, ()> {
+    // serde_json es la referencia de lo que es JSON válido
+    serde_json::from_str::<serde_json::Value>(source).map_err(|_| ())?;
+
+    let mut locator = Locator {
+        chars: source.chars().peekable(),
+        line: 1,
+        column: 1,
+        locations: HashMap::new(),
+    };
+    locator.value(String::new())?;
+    Ok(locator.locations)
+}
+
+// Subcomando `validate`: revisa el archivo sin necesitar Discord ni una
+// instancia en ejecución. Devuelve el código de salida del proceso.
+pub fn run_validate(path: Option<PathBuf>) -> i32 {
+    cli::attach_console();
+
+    let config_path = match path {
+        Some(path) => path,
+        None => match formats::active_config_path() {
+            Ok(config_path) => config_path,
+            Err(e) => {
+                eprintln!("❌ {}", e);
+                return 1;
+            }
+        },
+    };
+
+    let (config, diagnostics) = match check_config_file(&config_path, true) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            return 1;
+        }
+    };
+    for diagnostic in &diagnostics {
+        let icon = if diagnostic.is_error() {
+            "❌"
+        } else {
+            "⚠️ "
+        };
+        println!("{} {}", icon, diagnostic.display_with_file(&config_path));
+    }
+
+    let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+    let warnings = diagnostics.len() - errors;
+    if errors > 0 {
+        println!(
+            "❌ {}: {} errores, {} avisos",
+            config_path.display(),
+            errors,
+            warnings
+        );
+        1
+    } else {
+        let included = config
+            .as_ref()
+            .map(|c| includes::resolve(&config_path, &c.include).0.len())
+            .unwrap_or(0);
+        println!(
+            "✅ {} es válido ({} actividades, {} archivos incluidos, {} avisos)",
+            config_path.display(),
+            config.map(|c| c.discord.activities.len()).unwrap_or(0),
+            included,
+            warnings
+        );
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = r#"{
+  "discord": {
+    "app_id": "123456789012345678",
+    "activities": [
+      { "name": "uno", "details": "x", "state": "Estado", "duration_seconds": 5 },
+      {
+        "name": "dos",
+        "details": "Detalles",
+        "state": "Estado",
+        "duration_seconds": 5,
+        "buttons": [{ "label": "ñandú", "url": "ftp://x" }]
+      }
+    ]
+  }
+}"#;
+
+    fn location(diagnostics: &[Diagnostic], field: &str) -> Option<(usize, usize)> {
+        diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.field == field)
+            .unwrap_or_else(|| panic!("sin diagnóstico para {}: {:?}", field, diagnostics))
+            .location
+    }
+
+    #[test]
+    fn locates_nested_keys_and_arrays() {
+        let locations = locate_values(FIXTURE).expect("JSON válido");
+        assert_eq!(locations[""], (1, 1));
+        assert_eq!(locations["discord"], (2, 14));
+        assert_eq!(locations["discord.app_id"], (3, 15));
+        assert_eq!(locations["discord.activities"], (4, 19));
+        assert_eq!(locations["discord.activities[0]"], (5, 7));
+        assert_eq!(locations["discord.activities[0].details"], (5, 35));
+        assert_eq!(locations["discord.activities[1]"], (6, 7));
+        assert_eq!(locations["discord.activities[1].buttons"], (11, 20));
+        assert_eq!(locations["discord.activities[1].buttons[0]"], (11, 21));
+        // Las columnas cuentan caracteres, no bytes ("ñandú")
+        assert_eq!(
+            locations["discord.activities[1].buttons[0].label"],
+            (11, 32)
+        );
+        assert_eq!(locations["discord.activities[1].buttons[0].url"], (11, 48));
+    }
+
+    #[test]
+    fn locator_handles_escapes_and_empty_containers() {
+        let source =
+            "{\"a\": \"dice \\\"hola\\\"\", \"b\": [], \"c\": {},\n\"d\": [1, [true, null]]}";
+        let locations = locate_values(source).expect("JSON válido");
+        assert_eq!(locations["b"], (1, 29));
+        assert_eq!(locations["c"], (1, 38));
+        assert_eq!(locations["d"], (2, 6));
+        assert_eq!(locations["d[1][0]"], (2, 11));
+        assert_eq!(locations["d[1][1]"], (2, 17));
+        assert!(locate_values("{\"a\": ").is_err());
+    }
+
+    #[test]
+    fn diagnostics_point_at_the_field() {
+        let (_, diagnostics) = check_config_source(FIXTURE, formats::ConfigFormat::Json, false);
+        assert_eq!(
+            location(&diagnostics, "discord.activities[0].details"),
+            Some((5, 35))
+        );
+        assert_eq!(
+            location(&diagnostics, "discord.activities[1].buttons[0].url"),
+            Some((11, 48))
+        );
+        let url = diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.field.ends_with(".url"))
+            .expect("error de la URL");
+        assert_eq!(url.activity.as_deref(), Some("dos"));
+        assert!(url
+            .display_with_file(Path::new("/tmp/config.json"))
+            .starts_with("config.json:11:48: error [dos] discord.activities[1].buttons[0].url:"));
+    }
+
+    #[test]
+    fn type_errors_point_at_the_activity() {
+        let source = FIXTURE.replace(
+            "\"duration_seconds\": 5,\n",
+            "\"duration_seconds\": \"5\",\n",
+        );
+        let (config, diagnostics) =
+            check_config_source(&source, formats::ConfigFormat::Json, false);
+        assert!(config.is_none());
+        assert_eq!(
+            location(&diagnostics, "discord.activities[1]"),
+            Some((6, 7))
+        );
+    }
+
+    #[test]
+    fn syntax_errors_have_a_position() {
+        let source = "{\n  \"discord\": {\n    \"app_id\": \"1\",,\n  }\n}";
+        let (_, diagnostics) = check_config_source(source, formats::ConfigFormat::Json, false);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].field, "(sintaxis)");
+        assert_eq!(diagnostics[0].location.map(|(line, _)| line), Some(3));
+    }
+
+    #[test]
+    fn included_files_report_their_own_positions() {
+        let dir = env::temp_dir().join(format!("lorian-validate-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("directorio temporal");
+        let config_path = dir.join("config.json");
+        fs::write(
+            &config_path,
+            r#"{
+  "include": ["extra.json", "missing.json"],
+  "discord": { "app_id": "123456789012345678", "activities": [] }
+}"#,
+        )
+        .expect("config.json");
+        fs::write(
+            dir.join("extra.json"),
+            r#"{
+  "activities": [
+    {
+      "name": "incluida",
+      "details": "Detalles",
+      "state": "s",
+      "duration_seconds": 5
+    }
+  ]
+}"#,
+        )
+        .expect("extra.json");
+
+        let result = check_config_file(&config_path, true);
+        let _ = fs::remove_dir_all(&dir);
+        let (config, diagnostics) = result.expect("se puede leer");
+        assert_eq!(
+            config.map(|config| config.discord.activities.len()),
+            Some(1)
+        );
+
+        let state = diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.field == "activities[0].state")
+            .unwrap_or_else(|| panic!("sin error del estado: {:?}", diagnostics));
+        assert_eq!(
+            state.file.as_deref(),
+            Some(dir.join("extra.json").as_path())
+        );
+        assert_eq!(state.location, Some((6, 16)));
+        assert!(state
+            .display_with_file(&config_path)
+            .starts_with("extra.json:6:16: error [incluida] activities[0].state:"));
+
+        // El include que no existe se señala en el archivo principal
+        assert_eq!(location(&diagnostics, "include[1]"), Some((2, 29)));
+    }
+}
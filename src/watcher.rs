@@ -0,0 +1,284 @@
+// Vigilancia del archivo de configuración para el hot reload.
+//
+// Los editores guardan de formas muy distintas: escribir directamente
+// (modify), escribir un temporal y renombrarlo encima (VS Code, JetBrains),
+// o mover el original a un backup y crear uno nuevo (vim). Por eso se vigila
+// el directorio completo y se acepta cualquier evento que toque uno de los
+// archivos de configuración (config.json, config.toml...), y las ráfagas de
+// eventos de un mismo guardado se agrupan en una sola recarga con una
+// ventana de debounce.
+//
+// También recargan los cambios en activities.d/ y en los archivos o carpetas
+// de "include". El directorio de configuración se vigila sin recursión (con
+// --config puede ser cualquier carpeta, incluso el home); activities.d y las
+// carpetas de los includes se añaden aparte y la lista se actualiza en cada
+// recarga.
+
+use super::*;
+use crossbeam_channel::RecvTimeoutError;
+use notify::event::{AccessKind, AccessMode};
+use std::collections::HashSet;
+use std::sync::Mutex as StdMutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Tiempo sin eventos nuevos antes de recargar
+const DEBOUNCE_WINDOW: Duration = Duration::from_millis(300);
+// Aunque sigan llegando eventos, no esperar más que esto para recargar
+const MAX_DEBOUNCE_DELAY: Duration = Duration::from_secs(2);
+// Cada cuánto se comprueba si el directorio vigilado desapareció o volvió
+const DIR_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+fn has_config_extension(path: &Path) -> bool {
+    formats::ConfigFormat::from_path(path).is_some()
+}
+
+fn is_config_path(path: &Path, app_dir: &Path, includes: &[PathBuf]) -> bool {
+    // Archivo fijado con --config
+    if let Some(config_file) = paths::config_file_override() {
+        if path == config_file {
+            return true;
+        }
+    }
+
+    // Archivo principal. Crear un config.toml junto a config.json también
+    // cuenta: cambia cuál se usa
+    if paths::config_file_override().is_none()
+        && path.parent() == Some(app_dir)
+        && path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(formats::is_config_file_name)
+    {
+        return true;
+    }
+
+    // activities.d (la carpeta en sí o sus archivos de actividades)
+    let activities_dir = app_dir.join(includes::ACTIVITIES_DIR);
+    if path == activities_dir
+        || (path.parent() == Some(activities_dir.as_path()) && has_config_extension(path))
+    {
+        return true;
+    }
+
+    // Archivos incluidos, o archivos dentro de carpetas incluidas
+    includes.iter().any(|target| {
+        path == target || (path.parent() == Some(target.as_path()) && has_config_extension(path))
+    })
+}
+
+fn is_config_event(event: &Event, app_dir: &Path, includes: &[PathBuf]) -> bool {
+    match event.kind {
+        // Abrir o leer el archivo (incluido nuestro propio load_config) no
+        // es un cambio; solo cuenta cerrar tras escribir
+        EventKind::Access(AccessKind::Close(AccessMode::Write)) => {}
+        EventKind::Access(_) => return false,
+        _ => {}
+    }
+
+    // En un rename los paths son [origen, destino]: basta con que uno sea un
+    // archivo de configuración (temporal → config.json o config.json → backup)
+    event
+        .paths
+        .iter()
+        .any(|path| is_config_path(path, app_dir, includes))
+}
+
+pub fn start_config_watcher(command_sender: Sender<AppCommand>) -> StdResult<(), String> {
+    let app_dir = paths::ensure_config_dir()?;
+    let (event_sender, event_receiver) = unbounded::<()>();
+
+    // Rutas de "include" que el callback del watcher tiene que reconocer
+    let included_paths = Arc::new(StdMutex::new(Vec::<PathBuf>::new()));
+
+    let callback_app_dir = app_dir.clone();
+    let callback_includes = included_paths.clone();
+    let watcher = RecommendedWatcher::new(
+        move |res: StdResult<Event, notify::Error>| match res {
+            Ok(event) => {
+                let includes = callback_includes
+                    .lock()
+                    .map(|paths| paths.clone())
+                    .unwrap_or_default();
+                if is_config_event(&event, &callback_app_dir, &includes) {
+                    let _ = event_sender.send(());
+                }
+            }
+            Err(e) => log_error(&format!("⚠️  Error del file watcher: {:?}", e)),
+        },
+        NotifyConfig::default(),
+    )
+    .map_err(|e| format!("Error creando file watcher: {}", e))?;
+
+    let mut state = WatchState {
+        watcher,
+        app_dir,
+        command_sender,
+        config_missing: false,
+        included_paths,
+        external_dirs: HashSet::new(),
+    };
+
+    state
+        .watcher
+        .watch(&state.app_dir, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("Error vigilando {}: {}", state.app_dir.display(), e))?;
+    log_info(&format!(
+        "👁️  File watcher iniciado para: {}",
+        state.app_dir.display()
+    ));
+    state.refresh_includes();
+
+    thread::spawn(move || debounce_loop(state, event_receiver));
+    Ok(())
+}
+
+struct WatchState {
+    watcher: RecommendedWatcher,
+    app_dir: PathBuf,
+    command_sender: Sender<AppCommand>,
+    // No había ningún archivo de configuración en la última comprobación
+    config_missing: bool,
+    included_paths: Arc<StdMutex<Vec<PathBuf>>>,
+    // Carpetas vigiladas además del directorio de configuración
+    // (activities.d y las de los includes)
+    external_dirs: HashSet<PathBuf>,
+}
+
+impl WatchState {
+    fn request_reload(&mut self) {
+        // Un guardado por rename puede dejar un instante sin archivo; si de
+        // verdad se borró, no recargar (load_config crearía uno por defecto)
+        let Some(config_path) = formats::existing_config_file() else {
+            if !self.config_missing {
+                log_info(&format!(
+                    "🗑️  No queda ningún archivo de configuración en {} - se mantiene la configuración actual hasta que vuelva a existir",
+                    self.app_dir.display()
+                ));
+                self.config_missing = true;
+            }
+            return;
+        };
+
+        let file_name = config_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if self.config_missing {
+            log_info(&format!(
+                "📁 {} vuelve a existir - recargando...",
+                file_name
+            ));
+            self.config_missing = false;
+        } else {
+            log_info(&format!(
+                "📁 Detectado cambio en la configuración ({}) - recargando...",
+                file_name
+            ));
+        }
+        let _ = self.command_sender.send(AppCommand::ReloadConfig);
+
+        // La lista de includes puede haber cambiado con este guardado
+        self.refresh_includes();
+    }
+
+    // Vuelve a leer "include" y ajusta qué otras carpetas se vigilan
+    fn refresh_includes(&mut self) {
+        let targets = formats::existing_config_file()
+            .map(|config_path| includes::watched_paths(&config_path))
+            .unwrap_or_default();
+
+        // De un archivo se vigila su carpeta, así también se ve si se crea o
+        // se reemplaza con un rename
+        let wanted: HashSet<PathBuf> = targets
+            .iter()
+            .filter_map(|target| {
+                if target.is_dir() {
+                    Some(target.clone())
+                } else {
+                    target.parent().map(Path::to_path_buf)
+                }
+            })
+            .chain(std::iter::once(self.app_dir.join(includes::ACTIVITIES_DIR)))
+            .filter(|dir| *dir != self.app_dir && dir.is_dir())
+            .collect();
+
+        if let Ok(mut paths) = self.included_paths.lock() {
+            *paths = targets;
+        }
+
+        for dir in self.external_dirs.difference(&wanted) {
+            let _ = self.watcher.unwatch(dir);
+        }
+        let mut watched = HashSet::new();
+        for dir in wanted {
+            if self.external_dirs.contains(&dir) {
+                watched.insert(dir);
+                continue;
+            }
+            match self.watcher.watch(&dir, RecursiveMode::NonRecursive) {
+                Ok(()) => {
+                    log_info(&format!("👁️  Vigilando también: {}", dir.display()));
+                    watched.insert(dir);
+                }
+                Err(e) => log_error(&format!("⚠️  Error vigilando {}: {}", dir.display(), e)),
+            }
+        }
+        self.external_dirs = watched;
+    }
+}
+
+fn debounce_loop(mut state: WatchState, event_receiver: Receiver<()>) {
+    let mut watching = true;
+
+    loop {
+        match event_receiver.recv_timeout(DIR_CHECK_INTERVAL) {
+            Ok(()) => {
+                // Agrupar la ráfaga: esperar a que pase la ventana sin eventos
+                let burst_start = Instant::now();
+                while burst_start.elapsed() < MAX_DEBOUNCE_DELAY {
+                    match event_receiver.recv_timeout(DEBOUNCE_WINDOW) {
+                        Ok(()) => continue,
+                        Err(RecvTimeoutError::Timeout) => break,
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+                state.request_reload();
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                // Si se borra el directorio entero la vigilancia se pierde;
+                // volver a vigilarlo cuando reaparezca
+                let dir_exists = state.app_dir.exists();
+                if watching && !dir_exists {
+                    log_error(&format!(
+                        "⚠️  {} ya no existe - esperando a que vuelva a crearse",
+                        state.app_dir.display()
+                    ));
+                    let _ = state.watcher.unwatch(&state.app_dir);
+                    watching = false;
+                    state.config_missing = true;
+                } else if !watching && dir_exists {
+                    match state
+                        .watcher
+                        .watch(&state.app_dir, RecursiveMode::NonRecursive)
+                    {
+                        Ok(()) => {
+                            log_info(&format!(
+                                "👁️  File watcher restablecido para: {}",
+                                state.app_dir.display()
+                            ));
+                            watching = true;
+                            state.request_reload();
+                        }
+                        Err(e) => log_error(&format!(
+                            "⚠️  Error volviendo a vigilar {}: {}",
+                            state.app_dir.display(),
+                            e
+                        )),
+                    }
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}